@@ -1,22 +1,34 @@
-#![feature(get_mut_unchecked)]
+//! All classes are values: getters return copies and setters and `add_*`
+//! methods copy their arguments. So `ant.pose.x = 1.0` only changes a
+//! temporary copy; assign the modified pose back with `ant.pose = pose`.
+
+// The pyo3 0.14 macros re-borrow optional reference arguments
+#![allow(clippy::needless_option_as_deref)]
 
-use common;
 use pyo3::class::basic::PyObjectProtocol;
-use pyo3::exceptions::{PyFileNotFoundError, PyTypeError};
+use pyo3::class::iter::PyIterProtocol;
+use pyo3::class::mapping::PyMappingProtocol;
+use pyo3::exceptions::{PyFileNotFoundError, PyIndexError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PySlice};
+use pyo3::PyNativeType;
+use std::cell::RefCell;
 use std::convert::From;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
-use std::sync::Arc;
+use std::io::BufWriter;
+use std::os::raw::c_long;
+use std::path::Path;
 
 mod math;
+mod pickle;
+mod recording;
 mod world;
 use math::{PyPose, PyVector2};
 
-#[pyclass(name = "Map", subclass)]
+#[pyclass(name = "Map", module = "antbinding", subclass)]
 #[derive(Clone)]
 pub struct PyMap {
-    pub inner: Arc<common::Map>,
+    pub inner: common::Map,
 }
 
 #[pymethods]
@@ -24,13 +36,30 @@ impl PyMap {
     #[new]
     fn py_new() -> Self {
         Self {
-            inner: Arc::new(common::Map {
+            inner: common::Map {
                 width: 32.,
                 height: 32.,
-            }),
+            },
         }
     }
 
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        pickle::dumps(&self.inner)
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = PyMap::from(&pickle::loads::<common::Map>(state)?);
+        Ok(())
+    }
+
+    fn __copy__(&self) -> Self {
+        PyMap::from(&self.inner)
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> Self {
+        self.__copy__()
+    }
+
     #[getter]
     fn get_width(&self) -> PyResult<f32> {
         Ok(self.inner.width)
@@ -43,23 +72,13 @@ impl PyMap {
 
     #[setter]
     fn set_width(&mut self, width: f32) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.inner).width = width;
-        }
-        // Save alternative
-        // Rc::get_mut(&mut self.inner)
-        //     .ok_or(PyErr::new::<PyTypeError, _>(
-        //         "Could not lease mutable reference",
-        //     ))?
-        //     .width = width;
+        self.inner.width = width;
         Ok(())
     }
 
     #[setter]
     fn set_height(&mut self, height: f32) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.inner).height = height;
-        }
+        self.inner.height = height;
         Ok(())
     }
 }
@@ -73,15 +92,14 @@ impl PyObjectProtocol for PyMap {
 
 impl From<&common::Map> for PyMap {
     fn from(map: &common::Map) -> Self {
-        PyMap {
-            inner: Arc::new(map.clone()),
-        }
+        PyMap { inner: map.clone() }
     }
 }
 
-#[pyclass(name = "SugarHill", subclass)]
+#[pyclass(name = "SugarHill", module = "antbinding", subclass)]
 #[derive(Clone)]
 pub struct PySugarHill {
+    id: common::EntityId,
     pose: PyPose,
     volume: f32,
 }
@@ -89,13 +107,48 @@ pub struct PySugarHill {
 #[pymethods]
 impl PySugarHill {
     #[new]
-    fn py_new(x: Option<f32>, y: Option<f32>, rotation: Option<f32>, volume: Option<f32>) -> Self {
+    fn py_new(
+        x: Option<f32>,
+        y: Option<f32>,
+        rotation: Option<f32>,
+        volume: Option<f32>,
+        id: Option<common::EntityId>,
+    ) -> Self {
         Self {
+            id: id.unwrap_or(0),
             pose: PyPose::py_new(x, y, rotation),
             volume: volume.unwrap_or(12.),
         }
     }
 
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        pickle::dumps(&common::SugarHill::from(self))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = PySugarHill::from(pickle::loads::<common::SugarHill>(state)?);
+        Ok(())
+    }
+
+    fn __copy__(&self) -> Self {
+        PySugarHill::from(common::SugarHill::from(self))
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> Self {
+        self.__copy__()
+    }
+
+    #[getter]
+    fn get_id(&self) -> PyResult<common::EntityId> {
+        Ok(self.id)
+    }
+
+    #[setter]
+    fn set_id(&mut self, id: common::EntityId) -> PyResult<()> {
+        self.id = id;
+        Ok(())
+    }
+
     #[getter]
     fn get_volume(&self) -> PyResult<f32> {
         Ok(self.volume)
@@ -114,19 +167,16 @@ impl PySugarHill {
 
     #[setter]
     fn set_pose(&mut self, pose: PyPose) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.pose.inner).x = pose.inner.x;
-            Arc::get_mut_unchecked(&mut self.pose.inner).y = pose.inner.y;
-            Arc::get_mut_unchecked(&mut self.pose.inner).rotation = pose.inner.rotation;
-        }
+        self.pose = pose;
         Ok(())
     }
 }
 
 impl From<common::SugarHill> for PySugarHill {
     fn from(hill: common::SugarHill) -> Self {
-        let common::SugarHill { pose, volume } = hill;
+        let common::SugarHill { id, pose, volume } = hill;
         PySugarHill {
+            id,
             pose: PyPose::from(pose),
             volume,
         }
@@ -136,6 +186,7 @@ impl From<common::SugarHill> for PySugarHill {
 impl From<&PySugarHill> for common::SugarHill {
     fn from(py_hill: &PySugarHill) -> Self {
         common::SugarHill {
+            id: py_hill.id,
             pose: common::Pose::from(&py_hill.pose),
             volume: py_hill.volume,
         }
@@ -149,9 +200,10 @@ impl PyObjectProtocol for PySugarHill {
     }
 }
 
-#[pyclass(name = "Ant", subclass)]
+#[pyclass(name = "Ant", module = "antbinding", subclass)]
 #[derive(Clone)]
 pub struct PyAnt {
+    id: common::EntityId,
     pose: PyPose,
     team: u8,
     hp: f32,
@@ -168,8 +220,10 @@ impl PyAnt {
         team: Option<common::Team>,
         hp: Option<f32>,
         velocity: Option<f32>,
+        id: Option<common::EntityId>,
     ) -> Self {
         PyAnt {
+            id: id.unwrap_or(0),
             pose: PyPose::py_new(x, y, rotation),
             team: team.unwrap_or(0),
             hp: hp.unwrap_or(1.),
@@ -177,6 +231,34 @@ impl PyAnt {
         }
     }
 
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        pickle::dumps(&common::Ant::from(self))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = PyAnt::from(pickle::loads::<common::Ant>(state)?);
+        Ok(())
+    }
+
+    fn __copy__(&self) -> Self {
+        PyAnt::from(common::Ant::from(self))
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> Self {
+        self.__copy__()
+    }
+
+    #[getter]
+    fn get_id(&self) -> PyResult<common::EntityId> {
+        Ok(self.id)
+    }
+
+    #[setter]
+    fn set_id(&mut self, id: common::EntityId) -> PyResult<()> {
+        self.id = id;
+        Ok(())
+    }
+
     #[getter]
     fn get_team(&self) -> PyResult<common::Team> {
         Ok(self.team)
@@ -217,11 +299,7 @@ impl PyAnt {
 
     #[setter]
     fn set_pose(&mut self, pose: PyPose) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.pose.inner).x = pose.inner.x;
-            Arc::get_mut_unchecked(&mut self.pose.inner).y = pose.inner.y;
-            Arc::get_mut_unchecked(&mut self.pose.inner).rotation = pose.inner.rotation;
-        }
+        self.pose = pose;
         Ok(())
     }
 }
@@ -229,12 +307,14 @@ impl PyAnt {
 impl From<common::Ant> for PyAnt {
     fn from(hill: common::Ant) -> Self {
         let common::Ant {
+            id,
             pose,
             team,
             hp,
             velocity,
         } = hill;
         PyAnt {
+            id,
             pose: PyPose::from(pose),
             team,
             hp,
@@ -246,6 +326,7 @@ impl From<common::Ant> for PyAnt {
 impl From<&PyAnt> for common::Ant {
     fn from(py_ant: &PyAnt) -> Self {
         common::Ant {
+            id: py_ant.id,
             pose: common::Pose::from(&py_ant.pose),
             team: py_ant.team,
             hp: py_ant.hp,
@@ -261,9 +342,10 @@ impl PyObjectProtocol for PyAnt {
     }
 }
 
-#[pyclass(name = "AntHill", subclass)]
+#[pyclass(name = "AntHill", module = "antbinding", subclass)]
 #[derive(Clone)]
 pub struct PyAntHill {
+    id: common::EntityId,
     pose: PyPose,
     team: u8,
 }
@@ -276,13 +358,43 @@ impl PyAntHill {
         y: Option<f32>,
         rotation: Option<f32>,
         team: Option<common::Team>,
+        id: Option<common::EntityId>,
     ) -> Self {
         PyAntHill {
+            id: id.unwrap_or(0),
             pose: PyPose::py_new(x, y, rotation),
             team: team.unwrap_or(0),
         }
     }
 
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        pickle::dumps(&common::AntHill::from(self))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = PyAntHill::from(pickle::loads::<common::AntHill>(state)?);
+        Ok(())
+    }
+
+    fn __copy__(&self) -> Self {
+        PyAntHill::from(common::AntHill::from(self))
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> Self {
+        self.__copy__()
+    }
+
+    #[getter]
+    fn get_id(&self) -> PyResult<common::EntityId> {
+        Ok(self.id)
+    }
+
+    #[setter]
+    fn set_id(&mut self, id: common::EntityId) -> PyResult<()> {
+        self.id = id;
+        Ok(())
+    }
+
     #[getter]
     fn get_team(&self) -> PyResult<common::Team> {
         Ok(self.team)
@@ -301,19 +413,16 @@ impl PyAntHill {
 
     #[setter]
     fn set_pose(&mut self, pose: PyPose) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.pose.inner).x = pose.inner.x;
-            Arc::get_mut_unchecked(&mut self.pose.inner).y = pose.inner.y;
-            Arc::get_mut_unchecked(&mut self.pose.inner).rotation = pose.inner.rotation;
-        }
+        self.pose = pose;
         Ok(())
     }
 }
 
 impl From<common::AntHill> for PyAntHill {
     fn from(hill: common::AntHill) -> Self {
-        let common::AntHill { pose, team } = hill;
+        let common::AntHill { id, pose, team } = hill;
         PyAntHill {
+            id,
             pose: PyPose::from(pose),
             team,
         }
@@ -323,6 +432,7 @@ impl From<common::AntHill> for PyAntHill {
 impl From<&PyAntHill> for common::AntHill {
     fn from(py_hill: &PyAntHill) -> Self {
         common::AntHill {
+            id: py_hill.id,
             pose: common::Pose::from(&py_hill.pose),
             team: py_hill.team,
         }
@@ -336,9 +446,10 @@ impl PyObjectProtocol for PyAntHill {
     }
 }
 
-#[pyclass(name = "SmellCloud", subclass)]
+#[pyclass(name = "SmellCloud", module = "antbinding", subclass)]
 #[derive(Clone)]
 pub struct PySmellCloud {
+    id: common::EntityId,
     position: PyVector2,
     code: u32,
     age: u32,
@@ -354,8 +465,10 @@ impl PySmellCloud {
         team: Option<common::Team>,
         age: Option<u32>,
         code: Option<u32>,
+        id: Option<common::EntityId>,
     ) -> Self {
         Self {
+            id: id.unwrap_or(0),
             position: PyVector2::py_new(x, y),
             team: team.unwrap_or(0),
             age: age.unwrap_or(100),
@@ -363,6 +476,34 @@ impl PySmellCloud {
         }
     }
 
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        pickle::dumps(&common::SmellCloud::from(self))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = PySmellCloud::from(pickle::loads::<common::SmellCloud>(state)?);
+        Ok(())
+    }
+
+    fn __copy__(&self) -> Self {
+        PySmellCloud::from(common::SmellCloud::from(self))
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> Self {
+        self.__copy__()
+    }
+
+    #[getter]
+    fn get_id(&self) -> PyResult<common::EntityId> {
+        Ok(self.id)
+    }
+
+    #[setter]
+    fn set_id(&mut self, id: common::EntityId) -> PyResult<()> {
+        self.id = id;
+        Ok(())
+    }
+
     #[getter]
     fn get_team(&self) -> PyResult<common::Team> {
         Ok(self.team)
@@ -381,10 +522,7 @@ impl PySmellCloud {
 
     #[setter]
     fn set_position(&mut self, position: PyVector2) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.position.inner).x = position.inner.x;
-            Arc::get_mut_unchecked(&mut self.position.inner).y = position.inner.y;
-        }
+        self.position = position;
         Ok(())
     }
 }
@@ -392,12 +530,14 @@ impl PySmellCloud {
 impl From<common::SmellCloud> for PySmellCloud {
     fn from(hill: common::SmellCloud) -> Self {
         let common::SmellCloud {
+            id,
             position,
             team,
             age,
             code,
         } = hill;
         Self {
+            id,
             position: PyVector2::from(position),
             team,
             age,
@@ -409,6 +549,7 @@ impl From<common::SmellCloud> for PySmellCloud {
 impl From<&PySmellCloud> for common::SmellCloud {
     fn from(py_cloud: &PySmellCloud) -> Self {
         Self {
+            id: py_cloud.id,
             position: common::Vector2::from(&py_cloud.position),
             team: py_cloud.team,
             age: py_cloud.age,
@@ -424,19 +565,149 @@ impl PyObjectProtocol for PySmellCloud {
     }
 }
 
-#[pyclass(name = "Frame", subclass)]
+#[pyclass(name = "Event", module = "antbinding", subclass)]
+#[derive(Clone)]
+pub struct PyEvent {
+    inner: common::Event,
+}
+
+#[pymethods]
+impl PyEvent {
+    /// Takes the values the getters return, e.g.
+    /// `Event("fight_hit", entities=[attacker, target], team=0, damage=0.5)`
+    #[new]
+    fn py_new(
+        kind: &str,
+        entities: Option<Vec<common::EntityId>>,
+        team: Option<common::Team>,
+        damage: Option<f32>,
+        code: Option<u32>,
+    ) -> PyResult<Self> {
+        let entities = entities.unwrap_or_default();
+        let entity = |index: usize| {
+            entities.get(index).copied().ok_or_else(|| {
+                PyErr::new::<PyValueError, _>(format!(
+                    "{} event needs {} entities",
+                    kind,
+                    index + 1
+                ))
+            })
+        };
+        let required_team = || {
+            team.ok_or_else(|| {
+                PyErr::new::<PyValueError, _>(format!("{} event needs a team", kind))
+            })
+        };
+        let inner = match kind {
+            "spawn" => common::Event::Spawn {
+                ant: entity(0)?,
+                team: required_team()?,
+            },
+            "death" => common::Event::Death {
+                ant: entity(0)?,
+                team: required_team()?,
+            },
+            "pickup" => common::Event::Pickup {
+                ant: entity(0)?,
+                team: required_team()?,
+                sugar_hill: entity(1)?,
+            },
+            "delivery" => common::Event::Delivery {
+                ant: entity(0)?,
+                team: required_team()?,
+                anthill: entity(1)?,
+            },
+            "fight_hit" => common::Event::FightHit {
+                attacker: entity(0)?,
+                team: required_team()?,
+                target: entity(1)?,
+                damage: damage.unwrap_or(0.),
+            },
+            "smell_emitted" => common::Event::SmellEmitted {
+                smell_cloud: entity(0)?,
+                team: required_team()?,
+                code: code.unwrap_or(0),
+            },
+            "hill_depleted" => common::Event::HillDepleted {
+                sugar_hill: entity(0)?,
+            },
+            "match_end" => common::Event::MatchEnd { winner: team },
+            _ => {
+                return Err(PyErr::new::<PyValueError, _>(format!(
+                    "Unknown event kind {}",
+                    kind
+                )))
+            }
+        };
+        Ok(PyEvent { inner })
+    }
+
+    /// e.g. "spawn", "death" or "delivery"
+    #[getter]
+    fn get_kind(&self) -> PyResult<&'static str> {
+        Ok(self.inner.kind())
+    }
+
+    #[getter]
+    fn get_team(&self) -> PyResult<Option<common::Team>> {
+        Ok(self.inner.team())
+    }
+
+    /// IDs of all involved entities, the causing one first
+    #[getter]
+    fn get_entities(&self) -> PyResult<Vec<common::EntityId>> {
+        Ok(self.inner.entities())
+    }
+
+    #[getter]
+    fn get_damage(&self) -> PyResult<Option<f32>> {
+        match self.inner {
+            common::Event::FightHit { damage, .. } => Ok(Some(damage)),
+            _ => Ok(None),
+        }
+    }
+
+    #[getter]
+    fn get_code(&self) -> PyResult<Option<u32>> {
+        match self.inner {
+            common::Event::SmellEmitted { code, .. } => Ok(Some(code)),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for PyEvent {
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("{:?}", self.inner))
+    }
+}
+
+impl From<common::Event> for PyEvent {
+    fn from(event: common::Event) -> Self {
+        PyEvent { inner: event }
+    }
+}
+
+#[pyclass(name = "Frame", module = "antbinding", subclass)]
 #[derive(Clone)]
 pub struct PyFrame {
+    tick: u64,
+    time: f32,
     ants: Vec<PyAnt>,
     anthills: Vec<PyAntHill>,
     raspberries: Vec<PyPose>,
     sugar_hills: Vec<PySugarHill>,
     smells_clouds: Vec<PySmellCloud>,
+    events: Vec<common::Event>,
+    checksum: u64,
 }
 
 impl From<&PyFrame> for common::Frame {
     fn from(py_frame: &PyFrame) -> Self {
         common::Frame {
+            tick: py_frame.tick,
+            time: py_frame.time,
             ants: py_frame.ants.iter().map(common::Ant::from).collect(),
             anthills: py_frame
                 .anthills
@@ -458,6 +729,8 @@ impl From<&PyFrame> for common::Frame {
                 .iter()
                 .map(common::SmellCloud::from)
                 .collect(),
+            events: py_frame.events.clone(),
+            checksum: py_frame.checksum,
         }
     }
 }
@@ -465,18 +738,26 @@ impl From<&PyFrame> for common::Frame {
 impl From<common::Frame> for PyFrame {
     fn from(frame: common::Frame) -> Self {
         let common::Frame {
+            tick,
+            time,
             ants,
             anthills,
             raspberries,
             sugar_hills,
             smells_clouds,
+            events,
+            checksum,
         } = frame;
         PyFrame {
+            tick,
+            time,
             ants: ants.into_iter().map(PyAnt::from).collect(),
             anthills: anthills.into_iter().map(PyAntHill::from).collect(),
             raspberries: raspberries.into_iter().map(PyPose::from).collect(),
             sugar_hills: sugar_hills.into_iter().map(PySugarHill::from).collect(),
             smells_clouds: smells_clouds.into_iter().map(PySmellCloud::from).collect(),
+            events,
+            checksum,
         }
     }
 }
@@ -484,16 +765,70 @@ impl From<common::Frame> for PyFrame {
 #[pymethods]
 impl PyFrame {
     #[new]
-    fn py_new() -> Self {
+    fn py_new(tick: Option<u64>, time: Option<f32>) -> Self {
         PyFrame {
+            tick: tick.unwrap_or(0),
+            time: time.unwrap_or(0.),
             ants: vec![],
             anthills: vec![],
             raspberries: vec![],
             sugar_hills: vec![],
             smells_clouds: vec![],
+            events: vec![],
+            checksum: 0,
         }
     }
 
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        pickle::dumps(&common::Frame::from(self))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = PyFrame::from(pickle::loads::<common::Frame>(state)?);
+        Ok(())
+    }
+
+    fn __copy__(&self) -> Self {
+        PyFrame::from(common::Frame::from(self))
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> Self {
+        self.__copy__()
+    }
+
+    #[getter]
+    fn get_tick(&self) -> PyResult<u64> {
+        Ok(self.tick)
+    }
+
+    #[setter]
+    fn set_tick(&mut self, tick: u64) -> PyResult<()> {
+        self.tick = tick;
+        Ok(())
+    }
+
+    #[getter]
+    fn get_time(&self) -> PyResult<f32> {
+        Ok(self.time)
+    }
+
+    #[setter]
+    fn set_time(&mut self, time: f32) -> PyResult<()> {
+        self.time = time;
+        Ok(())
+    }
+
+    /// Hash of the state when the frame was simulated, 0 if unknown
+    #[getter]
+    fn get_checksum(&self) -> PyResult<u64> {
+        Ok(self.checksum)
+    }
+
+    /// Hash of the current state, equal to `checksum` unless modified
+    fn state_checksum(&self) -> PyResult<u64> {
+        Ok(common::Frame::from(self).state_checksum())
+    }
+
     #[getter]
     fn get_ants(&self) -> PyResult<Vec<PyAnt>> {
         Ok(self.ants.clone())
@@ -514,6 +849,16 @@ impl PyFrame {
         Ok(self.sugar_hills.clone())
     }
 
+    #[getter]
+    fn get_smell_clouds(&self) -> PyResult<Vec<PySmellCloud>> {
+        Ok(self.smells_clouds.clone())
+    }
+
+    #[getter]
+    fn get_events(&self) -> PyResult<Vec<PyEvent>> {
+        Ok(self.events.iter().cloned().map(PyEvent::from).collect())
+    }
+
     fn add_ant(&mut self, ant: PyAnt) -> PyResult<()> {
         self.ants.push(ant);
         Ok(())
@@ -533,12 +878,63 @@ impl PyFrame {
         self.sugar_hills.push(hill);
         Ok(())
     }
+
+    fn add_smell_cloud(&mut self, cloud: PySmellCloud) -> PyResult<()> {
+        self.smells_clouds.push(cloud);
+        Ok(())
+    }
+
+    fn add_event(&mut self, event: PyEvent) -> PyResult<()> {
+        self.events.push(event.inner);
+        Ok(())
+    }
 }
 
-#[pyclass(name = "Recording", subclass)]
+/// Frames of loaded recordings are decoded when accessed
+#[pyclass(name = "Recording", module = "antbinding", subclass)]
 pub struct PyRecording {
+    header: common::Header,
     map: PyMap,
-    frames: Vec<PyFrame>,
+    frames: RefCell<recording::Frames>,
+}
+
+impl PyRecording {
+    pub(crate) fn len(&self) -> usize {
+        self.frames.borrow().len()
+    }
+
+    pub(crate) fn frame(&self, index: usize) -> PyResult<common::Frame> {
+        self.frames
+            .borrow_mut()
+            .get(index)
+            .map_err(PyErr::new::<PyIndexError, _>)
+    }
+
+    /// Decodes all frames
+    fn to_common(&self) -> PyResult<common::Recording> {
+        Ok(common::Recording {
+            header: self.header.clone(),
+            map: self.map.inner.clone(),
+            frames: (0..self.len())
+                .map(|index| self.frame(index))
+                .collect::<PyResult<_>>()?,
+        })
+    }
+}
+
+impl From<common::Recording> for PyRecording {
+    fn from(recording: common::Recording) -> Self {
+        let common::Recording {
+            header,
+            map,
+            frames,
+        } = recording;
+        PyRecording {
+            header,
+            map: PyMap::from(&map),
+            frames: RefCell::new(recording::Frames::Memory(frames)),
+        }
+    }
 }
 
 #[pymethods]
@@ -546,17 +942,82 @@ impl PyRecording {
     #[new]
     fn py_new() -> Self {
         PyRecording {
+            header: common::Header {
+                engine_version: engine::VERSION.to_owned(),
+                ..common::Header::new()
+            },
             map: PyMap {
-                inner: Arc::new(common::Map {
+                inner: common::Map {
                     width: 128.,
                     height: 128.,
-                }),
+                },
             },
-            frames: vec![],
+            frames: RefCell::new(recording::Frames::Memory(vec![])),
         }
     }
 
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        pickle::dumps(&self.to_common()?)
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = PyRecording::from(pickle::loads::<common::Recording>(state)?);
+        Ok(())
+    }
+
+    /// Copies are kept in memory
+    fn __copy__(&self) -> PyResult<Self> {
+        Ok(PyRecording::from(self.to_common()?))
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> PyResult<Self> {
+        self.__copy__()
+    }
+
     // props
+    #[getter]
+    fn get_engine_version(&self) -> PyResult<String> {
+        Ok(self.header.engine_version.clone())
+    }
+
+    #[getter]
+    fn get_created(&self) -> PyResult<u64> {
+        Ok(self.header.created)
+    }
+
+    #[getter]
+    fn get_seed(&self) -> PyResult<Option<u64>> {
+        Ok(self.header.seed)
+    }
+
+    #[setter]
+    fn set_seed(&mut self, seed: Option<u64>) -> PyResult<()> {
+        self.header.seed = seed;
+        Ok(())
+    }
+
+    #[getter]
+    fn get_timestep(&self) -> PyResult<f32> {
+        Ok(self.header.timestep)
+    }
+
+    #[setter]
+    fn set_timestep(&mut self, timestep: f32) -> PyResult<()> {
+        self.header.timestep = timestep;
+        Ok(())
+    }
+
+    #[getter]
+    fn get_teams(&self) -> PyResult<Vec<String>> {
+        Ok(self.header.teams.clone())
+    }
+
+    #[setter]
+    fn set_teams(&mut self, teams: Vec<String>) -> PyResult<()> {
+        self.header.teams = teams;
+        Ok(())
+    }
+
     #[getter]
     fn get_map(&self) -> PyResult<PyMap> {
         Ok(PyMap {
@@ -570,14 +1031,59 @@ impl PyRecording {
         Ok(())
     }
 
+    /// Decodes all frames, prefer indexing or iterating for big recordings
     #[getter]
     fn frames(&self) -> PyResult<Vec<PyFrame>> {
-        Ok(self.frames.iter().cloned().collect())
+        (0..self.len())
+            .map(|index| self.frame(index).map(PyFrame::from))
+            .collect()
+    }
+
+    /// Pairs of tick and event, optionally only of one kind or team
+    #[pyo3(text_signature = "(self, kind=None, team=None, /)")]
+    fn events(
+        &self,
+        kind: Option<&str>,
+        team: Option<common::Team>,
+    ) -> PyResult<Vec<(u64, PyEvent)>> {
+        let mut events = Vec::new();
+        for index in 0..self.len() {
+            let frame = self.frame(index)?;
+            let tick = frame.tick;
+            events.extend(
+                frame
+                    .events
+                    .into_iter()
+                    .filter(|event| kind.is_none_or(|kind| event.kind() == kind))
+                    .filter(|event| team.is_none_or(|team| event.team() == Some(team)))
+                    .map(|event| (tick, PyEvent::from(event))),
+            );
+        }
+        Ok(events)
+    }
+
+    /// Iterates over pairs of tick and ant, optionally only of one team
+    #[pyo3(text_signature = "(self, team=None, /)")]
+    fn ants(slf: PyRef<Self>, team: Option<common::Team>) -> recording::PyRecordingIterator {
+        recording::PyRecordingIterator::new(slf.into(), recording::Selection::Ants(team))
+    }
+
+    /// Iterates over pairs of tick and smell cloud, optionally only of one team
+    #[pyo3(text_signature = "(self, team=None, /)")]
+    fn smell_clouds(
+        slf: PyRef<Self>,
+        team: Option<common::Team>,
+    ) -> recording::PyRecordingIterator {
+        recording::PyRecordingIterator::new(slf.into(), recording::Selection::SmellClouds(team))
     }
 
     #[pyo3(text_signature = "(self, frame, /)")]
     fn add_frame(&mut self, frame: PyFrame) -> PyResult<()> {
-        self.frames.push(frame);
+        self.frames
+            .get_mut()
+            .in_memory()
+            .map_err(PyErr::new::<PyTypeError, _>)?
+            .push(common::Frame::from(&frame));
         Ok(())
     }
 
@@ -585,51 +1091,102 @@ impl PyRecording {
     #[staticmethod]
     #[pyo3(text_signature = "(filename, /)")]
     fn load(filename: &str) -> PyResult<PyRecording> {
-        let file = File::open(filename)
-            .map_err(|msg| PyErr::new::<PyFileNotFoundError, _>(msg.to_string()))?;
-        let reader = BufReader::new(file);
-        let common::Recording { map, frames } =
-            common::Recording::load(reader).map_err(PyErr::new::<PyTypeError, _>)?;
-
+        let (frames, header, map) = recording::Frames::open(filename)?;
         Ok(PyRecording {
-            map: PyMap {
-                inner: Arc::new(map),
-            },
-            frames: frames.into_iter().map(PyFrame::from).collect(),
+            header,
+            map: PyMap { inner: map },
+            frames: RefCell::new(frames),
         })
     }
 
     #[pyo3(text_signature = "(self, filename, /)")]
     fn dump(&self, filename: &str) -> PyResult<()> {
+        // Overwriting the file the frames are read from
+        let source = self.frames.borrow().source().map(Path::to_path_buf);
+        if source.is_some() && source == std::fs::canonicalize(filename).ok() {
+            self.frames
+                .borrow_mut()
+                .in_memory()
+                .map_err(PyErr::new::<PyTypeError, _>)?;
+        }
         let file = File::create(filename)
             .map_err(|msg| PyErr::new::<PyFileNotFoundError, _>(msg.to_string()))?;
-        let writer = BufWriter::new(file);
-        let recording = common::Recording {
-            map: (*self.map.inner).clone(),
-            frames: self
-                .frames
-                .iter()
-                .map(|frame| common::Frame::from(frame))
-                .collect(),
-        };
-        recording
-            .dump(writer)
+        let mut writer = common::recording::RecordingWriter::new(
+            BufWriter::new(file),
+            &self.header,
+            &self.map.inner,
+        )
+        .map_err(PyErr::new::<PyTypeError, _>)?;
+        for index in 0..self.len() {
+            writer
+                .write_frame(&self.frame(index)?)
+                .map_err(PyErr::new::<PyTypeError, _>)?;
+        }
+        writer
+            .finish(self.header.outcome.clone())
             .map_err(PyErr::new::<PyTypeError, _>)?;
         Ok(())
     }
 }
 
+#[pyproto]
+impl PyMappingProtocol for PyRecording {
+    fn __len__(&self) -> usize {
+        self.len()
+    }
+
+    /// Frame at an index, negative ones counting from the end, or a list of
+    /// frames for a slice
+    fn __getitem__(&self, key: &PyAny) -> PyResult<PyObject> {
+        let py = key.py();
+        let len = self.len();
+        if let Ok(slice) = key.downcast::<PySlice>() {
+            let indices = slice.indices(len as c_long)?;
+            let frames = (0..indices.slicelength)
+                .map(|i| {
+                    self.frame((indices.start + i * indices.step) as usize)
+                        .map(PyFrame::from)
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            return Ok(frames.into_py(py));
+        }
+        let index: isize = key.extract()?;
+        let index = if index < 0 {
+            index + len as isize
+        } else {
+            index
+        };
+        if index < 0 || index >= len as isize {
+            return Err(PyErr::new::<PyIndexError, _>(
+                "recording index out of range",
+            ));
+        }
+        Ok(PyFrame::from(self.frame(index as usize)?).into_py(py))
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for PyRecording {
+    fn __iter__(slf: PyRef<Self>) -> recording::PyRecordingIterator {
+        recording::PyRecordingIterator::new(slf.into(), recording::Selection::Frames)
+    }
+}
+
 #[pymodule]
 fn antbinding(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyPose>()?;
     m.add_class::<PyVector2>()?;
     m.add_class::<PyFrame>()?;
+    m.add_class::<PyEvent>()?;
     m.add_class::<PyMap>()?;
     m.add_class::<PyAnt>()?;
     m.add_class::<PyAntHill>()?;
     m.add_class::<PySugarHill>()?;
     m.add_class::<PySmellCloud>()?;
     m.add_class::<PyRecording>()?;
+    m.add_class::<recording::PyRecordingWriter>()?;
+    m.add_class::<recording::PyRecordingReader>()?;
+    m.add_class::<recording::PyRecordingIterator>()?;
     m.add_class::<world::PyWorld>()?;
     Ok(())
 }
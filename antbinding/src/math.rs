@@ -1,12 +1,26 @@
+use crate::pickle;
 use nalgebra::Isometry2;
-use pyo3::class::basic::PyObjectProtocol;
+use pyo3::class::basic::{CompareOp, PyObjectProtocol};
+use pyo3::class::number::PyNumberProtocol;
+use pyo3::exceptions::PyTypeError;
 use pyo3::prelude::*;
-use std::sync::Arc;
+use pyo3::types::PyBytes;
 
-#[pyclass(name = "Vector2", subclass)]
+const DEFAULT_EPSILON: f32 = 1e-6;
+
+/// Only equality is defined, there is no order
+fn compare(equal: bool, op: CompareOp) -> PyResult<bool> {
+    match op {
+        CompareOp::Eq => Ok(equal),
+        CompareOp::Ne => Ok(!equal),
+        _ => Err(PyErr::new::<PyTypeError, _>("Only == and != are supported")),
+    }
+}
+
+#[pyclass(name = "Vector2", module = "antbinding", subclass)]
 #[derive(Clone)]
 pub struct PyVector2 {
-    pub inner: Arc<common::Vector2>,
+    pub inner: common::Vector2,
 }
 
 #[pymethods]
@@ -14,13 +28,30 @@ impl PyVector2 {
     #[new]
     pub(crate) fn py_new(x: Option<f32>, y: Option<f32>) -> Self {
         Self {
-            inner: Arc::new(common::Vector2 {
+            inner: common::Vector2 {
                 x: x.unwrap_or(0.),
                 y: y.unwrap_or(0.),
-            }),
+            },
         }
     }
 
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        pickle::dumps(&common::Vector2::from(self))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = PyVector2::from(pickle::loads::<common::Vector2>(state)?);
+        Ok(())
+    }
+
+    fn __copy__(&self) -> Self {
+        PyVector2::from(common::Vector2::from(self))
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> Self {
+        self.__copy__()
+    }
+
     #[getter]
     fn get_x(&self) -> PyResult<f32> {
         Ok(self.inner.x)
@@ -33,19 +64,44 @@ impl PyVector2 {
 
     #[setter]
     fn set_x(&mut self, x: f32) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.inner).x = x;
-        }
+        self.inner.x = x;
         Ok(())
     }
 
     #[setter]
     fn set_y(&mut self, y: f32) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.inner).y = y;
-        }
+        self.inner.y = y;
         Ok(())
     }
+
+    fn dot(&self, other: PyVector2) -> f32 {
+        self.inner.dot(other.inner)
+    }
+
+    fn length(&self) -> f32 {
+        self.inner.length()
+    }
+
+    fn distance(&self, other: PyVector2) -> f32 {
+        self.inner.distance(other.inner)
+    }
+
+    fn normalize(&self) -> PyVector2 {
+        PyVector2::from(self.inner.normalize())
+    }
+
+    fn angle_to(&self, other: PyVector2) -> f32 {
+        self.inner.angle_to(other.inner)
+    }
+
+    fn rotate(&self, angle: f32) -> PyVector2 {
+        PyVector2::from(self.inner.rotate(angle))
+    }
+
+    fn approx_eq(&self, other: PyVector2, epsilon: Option<f32>) -> bool {
+        self.inner
+            .approx_eq(other.inner, epsilon.unwrap_or(DEFAULT_EPSILON))
+    }
 }
 
 #[pyproto]
@@ -53,26 +109,51 @@ impl PyObjectProtocol for PyVector2 {
     fn __repr__(&self) -> PyResult<String> {
         Ok(format!("{:?}", self.inner))
     }
+
+    fn __richcmp__(&self, other: PyVector2, op: CompareOp) -> PyResult<bool> {
+        compare(self.inner == other.inner, op)
+    }
+}
+
+#[pyproto]
+impl PyNumberProtocol for PyVector2 {
+    fn __add__(lhs: PyVector2, rhs: PyVector2) -> PyVector2 {
+        PyVector2::from(lhs.inner + rhs.inner)
+    }
+
+    fn __sub__(lhs: PyVector2, rhs: PyVector2) -> PyVector2 {
+        PyVector2::from(lhs.inner - rhs.inner)
+    }
+
+    fn __mul__(lhs: PyVector2, rhs: f32) -> PyVector2 {
+        PyVector2::from(lhs.inner * rhs)
+    }
+
+    fn __rmul__(&self, other: f32) -> PyVector2 {
+        PyVector2::from(other * self.inner)
+    }
+
+    fn __neg__(&self) -> PyVector2 {
+        PyVector2::from(-self.inner)
+    }
 }
 
 impl From<&PyVector2> for common::Vector2 {
     fn from(py_vector: &PyVector2) -> Self {
-        py_vector.inner.as_ref().clone()
+        py_vector.inner
     }
 }
 
 impl From<common::Vector2> for PyVector2 {
     fn from(vector: common::Vector2) -> Self {
-        PyVector2 {
-            inner: Arc::new(vector),
-        }
+        PyVector2 { inner: vector }
     }
 }
 
-#[pyclass(name = "Pose", subclass)]
+#[pyclass(name = "Pose", module = "antbinding", subclass)]
 #[derive(Clone)]
 pub struct PyPose {
-    pub inner: Arc<common::Pose>,
+    pub inner: common::Pose,
 }
 
 #[pymethods]
@@ -80,14 +161,31 @@ impl PyPose {
     #[new]
     pub(crate) fn py_new(x: Option<f32>, y: Option<f32>, rotation: Option<f32>) -> Self {
         PyPose {
-            inner: Arc::new(common::Pose {
+            inner: common::Pose {
                 x: x.unwrap_or(0.),
                 y: y.unwrap_or(0.),
                 rotation: rotation.unwrap_or(0.),
-            }),
+            },
         }
     }
 
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        pickle::dumps(&common::Pose::from(self))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = PyPose::from(pickle::loads::<common::Pose>(state)?);
+        Ok(())
+    }
+
+    fn __copy__(&self) -> Self {
+        PyPose::from(common::Pose::from(self))
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> Self {
+        self.__copy__()
+    }
+
     #[getter]
     fn get_x(&self) -> PyResult<f32> {
         Ok(self.inner.x)
@@ -105,27 +203,54 @@ impl PyPose {
 
     #[setter]
     fn set_x(&mut self, x: f32) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.inner).x = x;
-        }
+        self.inner.x = x;
         Ok(())
     }
 
     #[setter]
     fn set_y(&mut self, y: f32) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.inner).y = y;
-        }
+        self.inner.y = y;
         Ok(())
     }
 
     #[setter]
     fn set_rotation(&mut self, rotation: f32) -> PyResult<()> {
-        unsafe {
-            Arc::get_mut_unchecked(&mut self.inner).rotation = rotation;
-        }
+        self.inner.rotation = rotation;
         Ok(())
     }
+
+    fn position(&self) -> PyVector2 {
+        PyVector2::from(self.inner.position())
+    }
+
+    fn direction(&self) -> PyVector2 {
+        PyVector2::from(self.inner.direction())
+    }
+
+    fn compose(&self, other: PyPose) -> PyPose {
+        PyPose::from(self.inner.compose(other.inner))
+    }
+
+    fn inverse(&self) -> PyPose {
+        PyPose::from(self.inner.inverse())
+    }
+
+    fn to_local(&self, point: PyVector2) -> PyVector2 {
+        PyVector2::from(self.inner.to_local(point.inner))
+    }
+
+    fn to_world(&self, point: PyVector2) -> PyVector2 {
+        PyVector2::from(self.inner.to_world(point.inner))
+    }
+
+    fn angle_to(&self, point: PyVector2) -> f32 {
+        self.inner.angle_to(point.inner)
+    }
+
+    fn approx_eq(&self, other: PyPose, epsilon: Option<f32>) -> bool {
+        self.inner
+            .approx_eq(other.inner, epsilon.unwrap_or(DEFAULT_EPSILON))
+    }
 }
 
 #[pyproto]
@@ -133,25 +258,33 @@ impl PyObjectProtocol for PyPose {
     fn __repr__(&self) -> PyResult<String> {
         Ok(format!("{:?}", self.inner))
     }
+
+    fn __richcmp__(&self, other: PyPose, op: CompareOp) -> PyResult<bool> {
+        compare(self.inner == other.inner, op)
+    }
+}
+
+#[pyproto]
+impl PyNumberProtocol for PyPose {
+    fn __mul__(lhs: PyPose, rhs: PyPose) -> PyPose {
+        PyPose::from(lhs.inner * rhs.inner)
+    }
 }
 
 impl From<&PyPose> for common::Pose {
     fn from(py_pose: &PyPose) -> Self {
-        py_pose.inner.as_ref().clone()
+        py_pose.inner
     }
 }
 
 impl From<&PyPose> for Isometry2<f32> {
     fn from(py_pose: &PyPose) -> Self {
-        let pose = py_pose.inner.as_ref();
-        Isometry2::new(nalgebra::Vector2::new(pose.x, pose.y), pose.rotation)
+        Isometry2::from(py_pose.inner)
     }
 }
 
 impl From<common::Pose> for PyPose {
     fn from(pose: common::Pose) -> Self {
-        PyPose {
-            inner: Arc::new(pose),
-        }
+        PyPose { inner: pose }
     }
 }
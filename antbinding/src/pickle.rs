@@ -0,0 +1,23 @@
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// State for `__getstate__`, the bincode encoding of the common type
+///
+/// Takes the GIL itself instead of a `Python` argument: pyo3 0.14 calls
+/// methods with arguments through `METH_FASTCALL`, which builds a slice from
+/// the null argument array Python passes to `__getstate__`.
+pub(crate) fn dumps<T: Serialize>(value: &T) -> PyResult<PyObject> {
+    let bytes =
+        bincode::serialize(value).map_err(|msg| PyErr::new::<PyTypeError, _>(msg.to_string()))?;
+    Ok(Python::with_gil(|py| {
+        PyBytes::new(py, &bytes).to_object(py)
+    }))
+}
+
+pub(crate) fn loads<T: DeserializeOwned>(state: &PyBytes) -> PyResult<T> {
+    bincode::deserialize(state.as_bytes())
+        .map_err(|msg| PyErr::new::<PyTypeError, _>(msg.to_string()))
+}
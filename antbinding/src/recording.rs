@@ -0,0 +1,409 @@
+use crate::{PyAnt, PyFrame, PyMap, PyRecording, PySmellCloud};
+use pyo3::class::iter::PyIterProtocol;
+use pyo3::exceptions::{PyFileNotFoundError, PyIndexError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyAny;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Frames of a `Recording`, either in memory or decoded from its file one
+/// block at a time when accessed.
+pub(crate) enum Frames {
+    Memory(Vec<common::Frame>),
+    File {
+        path: PathBuf,
+        reader: Box<common::recording::RecordingReader<BufReader<File>>>,
+        count: usize,
+        first: usize, // index of the first frame in `block`
+        block: Vec<common::Frame>,
+    },
+}
+
+impl Frames {
+    pub fn open(filename: &str) -> PyResult<(Self, common::Header, common::Map)> {
+        let file = File::open(filename)
+            .map_err(|msg| PyErr::new::<PyFileNotFoundError, _>(msg.to_string()))?;
+        let path = std::fs::canonicalize(filename)
+            .map_err(|msg| PyErr::new::<PyFileNotFoundError, _>(msg.to_string()))?;
+        Self::open_file(path, file).map_err(PyErr::new::<PyTypeError, _>)
+    }
+
+    fn open_file(path: PathBuf, file: File) -> Result<(Self, common::Header, common::Map), String> {
+        let mut reader = common::recording::RecordingReader::new(BufReader::new(file))?;
+        let count = reader.frame_count()?;
+        reader.outcome()?;
+        let (header, map) = (reader.header.clone(), reader.map.clone());
+        Ok((
+            Frames::File {
+                path,
+                reader: Box::new(reader),
+                count,
+                first: 0,
+                block: vec![],
+            },
+            header,
+            map,
+        ))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Frames::Memory(frames) => frames.len(),
+            Frames::File { count, .. } => *count,
+        }
+    }
+
+    pub fn get(&mut self, index: usize) -> Result<common::Frame, String> {
+        match self {
+            Frames::Memory(frames) => frames
+                .get(index)
+                .cloned()
+                .ok_or_else(|| format!("Frame {} is out of range", index)),
+            Frames::File {
+                reader,
+                first,
+                block,
+                ..
+            } => {
+                if index < *first || index >= *first + block.len() {
+                    let entry = reader
+                        .index()?
+                        .iter()
+                        .find(|entry| {
+                            entry.first_frame as usize <= index
+                                && index < (entry.first_frame + entry.frames as u64) as usize
+                        })
+                        .cloned()
+                        .ok_or_else(|| format!("Frame {} is out of range", index))?;
+                    let start = entry.first_frame as usize;
+                    *block = reader.frames(start..start + entry.frames as usize)?;
+                    *first = start;
+                }
+                block
+                    .get(index - *first)
+                    .cloned()
+                    .ok_or_else(|| format!("Frame {} is out of range", index))
+            }
+        }
+    }
+
+    /// File the frames are read from
+    pub fn source(&self) -> Option<&Path> {
+        match self {
+            Frames::Memory(_) => None,
+            Frames::File { path, .. } => Some(path),
+        }
+    }
+
+    /// Decodes all frames, e.g. before modifying them
+    pub fn in_memory(&mut self) -> Result<&mut Vec<common::Frame>, String> {
+        if let Frames::File { .. } = self {
+            let frames = (0..self.len())
+                .map(|index| self.get(index))
+                .collect::<Result<Vec<_>, _>>()?;
+            *self = Frames::Memory(frames);
+        }
+        match self {
+            Frames::Memory(frames) => Ok(frames),
+            Frames::File { .. } => unreachable!(),
+        }
+    }
+}
+
+pub(crate) enum Selection {
+    Frames,
+    Ants(Option<common::Team>),
+    SmellClouds(Option<common::Team>),
+}
+
+/// Walks through a `Recording` decoding one frame at a time
+#[pyclass(name = "RecordingIterator", module = "antbinding")]
+pub struct PyRecordingIterator {
+    recording: Py<PyRecording>,
+    next_frame: usize,
+    selection: Selection,
+    pending: VecDeque<PyObject>,
+}
+
+impl PyRecordingIterator {
+    pub(crate) fn new(recording: Py<PyRecording>, selection: Selection) -> Self {
+        PyRecordingIterator {
+            recording,
+            next_frame: 0,
+            selection,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for PyRecordingIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<PyObject>> {
+        Python::with_gil(|py| loop {
+            if let Some(item) = slf.pending.pop_front() {
+                return Ok(Some(item));
+            }
+            let frame = {
+                let recording = slf.recording.borrow(py);
+                if slf.next_frame >= recording.len() {
+                    return Ok(None);
+                }
+                recording.frame(slf.next_frame)?
+            };
+            slf.next_frame += 1;
+            let tick = frame.tick;
+            let items = match slf.selection {
+                Selection::Frames => vec![PyFrame::from(frame).into_py(py)],
+                Selection::Ants(team) => frame
+                    .ants
+                    .into_iter()
+                    .filter(|ant| team.is_none_or(|team| ant.team == team))
+                    .map(|ant| (tick, PyAnt::from(ant)).into_py(py))
+                    .collect(),
+                Selection::SmellClouds(team) => frame
+                    .smells_clouds
+                    .into_iter()
+                    .filter(|cloud| team.is_none_or(|team| cloud.team == team))
+                    .map(|cloud| (tick, PySmellCloud::from(cloud)).into_py(py))
+                    .collect(),
+            };
+            slf.pending.extend(items);
+        })
+    }
+}
+
+#[pyclass(name = "RecordingWriter", module = "antbinding", subclass)]
+pub struct PyRecordingWriter {
+    inner: Option<common::recording::RecordingWriter<BufWriter<File>>>,
+}
+
+impl PyRecordingWriter {
+    fn try_get_mut(
+        &mut self,
+    ) -> PyResult<&mut common::recording::RecordingWriter<BufWriter<File>>> {
+        self.inner
+            .as_mut()
+            .ok_or_else(|| PyErr::new::<PyValueError, _>("recording was already finished"))
+    }
+}
+
+#[pymethods]
+impl PyRecordingWriter {
+    #[new]
+    fn py_new(
+        filename: &str,
+        map: Option<PyMap>,
+        seed: Option<u64>,
+        teams: Option<Vec<String>>,
+        compression: Option<i32>,
+    ) -> PyResult<Self> {
+        let file = File::create(filename)
+            .map_err(|msg| PyErr::new::<PyFileNotFoundError, _>(msg.to_string()))?;
+        let header = common::Header {
+            engine_version: engine::VERSION.to_owned(),
+            seed,
+            teams: teams.unwrap_or_default(),
+            ..common::Header::new()
+        };
+        let map = map
+            .map(|map| map.inner)
+            .unwrap_or_else(|| common::Recording::new().map);
+        let mut writer =
+            common::recording::RecordingWriter::new(BufWriter::new(file), &header, &map)
+                .map_err(PyErr::new::<PyTypeError, _>)?;
+        writer
+            .set_compression(compression)
+            .map_err(PyErr::new::<PyValueError, _>)?;
+        Ok(Self {
+            inner: Some(writer),
+        })
+    }
+
+    #[pyo3(text_signature = "(self, frame, /)")]
+    fn write(&mut self, frame: &PyFrame) -> PyResult<()> {
+        self.try_get_mut()?
+            .write_frame(&common::Frame::from(frame))
+            .map_err(PyErr::new::<PyTypeError, _>)
+    }
+
+    /// Writes all frames so far to the file, so that they are kept even if
+    /// the process gets killed
+    #[pyo3(text_signature = "(self, /)")]
+    fn flush(&mut self) -> PyResult<()> {
+        self.try_get_mut()?
+            .flush()
+            .map_err(PyErr::new::<PyTypeError, _>)
+    }
+
+    #[pyo3(text_signature = "(self, winner=None, scores=None, /)")]
+    fn finish(&mut self, winner: Option<common::Team>, scores: Option<Vec<f32>>) -> PyResult<()> {
+        let writer = self
+            .inner
+            .take()
+            .ok_or_else(|| PyErr::new::<PyValueError, _>("recording was already finished"))?;
+        let outcome = if winner.is_some() || scores.is_some() {
+            Some(common::Outcome {
+                winner,
+                scores: scores.unwrap_or_default(),
+            })
+        } else {
+            None
+        };
+        writer
+            .finish(outcome)
+            .map_err(PyErr::new::<PyTypeError, _>)?;
+        Ok(())
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    /// Finishes the recording, unless the block raised. Then the frames are
+    /// kept but the recording stays unfinished, like that of a crashed match.
+    fn __exit__(
+        &mut self,
+        exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> PyResult<bool> {
+        if exc_type.is_some() {
+            // Dropping the writer only writes the buffered frames
+            self.inner = None;
+        } else if self.inner.is_some() {
+            self.finish(None, None)?;
+        }
+        Ok(false)
+    }
+}
+
+#[pyclass(name = "RecordingReader", module = "antbinding", subclass)]
+pub struct PyRecordingReader {
+    inner: common::recording::RecordingReader<BufReader<File>>,
+}
+
+#[pymethods]
+impl PyRecordingReader {
+    #[new]
+    fn py_new(filename: &str) -> PyResult<Self> {
+        let file = File::open(filename)
+            .map_err(|msg| PyErr::new::<PyFileNotFoundError, _>(msg.to_string()))?;
+        let reader = common::recording::RecordingReader::new(BufReader::new(file))
+            .map_err(PyErr::new::<PyTypeError, _>)?;
+        Ok(Self { inner: reader })
+    }
+
+    #[getter]
+    fn get_map(&self) -> PyResult<PyMap> {
+        Ok(PyMap::from(&self.inner.map))
+    }
+
+    #[getter]
+    fn get_seed(&self) -> PyResult<Option<u64>> {
+        Ok(self.inner.header.seed)
+    }
+
+    #[getter]
+    fn get_timestep(&self) -> PyResult<f32> {
+        Ok(self.inner.header.timestep)
+    }
+
+    #[getter]
+    fn get_teams(&self) -> PyResult<Vec<String>> {
+        Ok(self.inner.header.teams.clone())
+    }
+
+    /// Only known after all frames were read
+    #[getter]
+    fn get_winner(&self) -> PyResult<Option<common::Team>> {
+        Ok(self
+            .inner
+            .header
+            .outcome
+            .as_ref()
+            .and_then(|outcome| outcome.winner))
+    }
+
+    /// Only known after all frames were read
+    #[getter]
+    fn get_scores(&self) -> PyResult<Option<Vec<f32>>> {
+        Ok(self
+            .inner
+            .header
+            .outcome
+            .as_ref()
+            .map(|outcome| outcome.scores.clone()))
+    }
+
+    /// Number of frames without decoding them
+    fn frame_count(&mut self) -> PyResult<usize> {
+        self.inner
+            .frame_count()
+            .map_err(PyErr::new::<PyTypeError, _>)
+    }
+
+    /// Decodes only the block containing this frame.
+    /// Iterating continues after that frame.
+    #[pyo3(text_signature = "(self, index, /)")]
+    fn frame(&mut self, index: usize) -> PyResult<PyFrame> {
+        self.inner
+            .frame(index)
+            .map(PyFrame::from)
+            .map_err(PyErr::new::<PyIndexError, _>)
+    }
+
+    /// Decodes only the block containing this tick.
+    /// Iterating continues with the first frame at or after the tick.
+    #[pyo3(text_signature = "(self, tick, /)")]
+    fn seek_tick(&mut self, tick: u64) -> PyResult<()> {
+        self.inner
+            .seek_tick(tick)
+            .map_err(PyErr::new::<PyIndexError, _>)
+    }
+
+    #[pyo3(text_signature = "(self, start, stop, /)")]
+    fn frames(&mut self, start: usize, stop: usize) -> PyResult<Vec<PyFrame>> {
+        Ok(self
+            .inner
+            .frames(start..stop)
+            .map_err(PyErr::new::<PyIndexError, _>)?
+            .into_iter()
+            .map(PyFrame::from)
+            .collect())
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> PyResult<bool> {
+        Ok(false)
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for PyRecordingReader {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<PyFrame>> {
+        match slf.inner.next() {
+            Some(frame) => Ok(Some(PyFrame::from(
+                frame.map_err(PyErr::new::<PyTypeError, _>)?,
+            ))),
+            None => Ok(None),
+        }
+    }
+}
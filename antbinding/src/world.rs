@@ -1,25 +1,30 @@
-use crate::{PyFrame, PyMap, PyPose};
+use crate::{PyEvent, PyFrame, PyMap, PyPose};
 use nalgebra::Isometry2;
-use pyo3::exceptions::{PyLookupError, PyReferenceError};
+use pyo3::exceptions::{PyFileNotFoundError, PyLookupError, PyTypeError};
 use pyo3::prelude::*;
-use std::sync::Arc;
+use pyo3::types::PyBytes;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
 
 use engine::World;
 
-#[pyclass(name = "World", subclass)]
+#[pyclass(name = "World", module = "antbinding", subclass)]
 pub struct PyWorld {
-    pub(crate) inner: Arc<World>,
+    pub(crate) inner: World,
 }
 
 impl PyWorld {
-    fn try_get_mut(&mut self) -> PyResult<&mut World> {
-        if let Some(world) = Arc::get_mut(&mut self.inner) {
-            Ok(world)
-        } else {
-            Err(PyErr::new::<PyReferenceError, _>(
-                "map was borrowed elsewhere",
-            ))
-        }
+    fn state(&self) -> PyResult<Vec<u8>> {
+        let mut state = Vec::new();
+        self.inner
+            .save(&mut state)
+            .map_err(PyErr::new::<PyTypeError, _>)?;
+        Ok(state)
+    }
+
+    fn from_state(state: &[u8]) -> PyResult<Self> {
+        let world = World::restore(state).map_err(PyErr::new::<PyTypeError, _>)?;
+        Ok(Self { inner: world })
     }
 }
 
@@ -28,30 +33,83 @@ impl PyWorld {
     #[new]
     pub(crate) fn py_new(width: Option<f32>, height: Option<f32>) -> Self {
         Self {
-            inner: Arc::new(World::new(width.unwrap_or(32.), height.unwrap_or(32.))),
+            inner: World::new(width.unwrap_or(32.), height.unwrap_or(32.)),
         }
     }
 
+    /// Pickled through `save` and `restore`
+    fn __getstate__(&self) -> PyResult<PyObject> {
+        let state = self.state()?;
+        Ok(Python::with_gil(|py| {
+            PyBytes::new(py, &state).to_object(py)
+        }))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = Self::from_state(state.as_bytes())?;
+        Ok(())
+    }
+
+    fn __copy__(&self) -> PyResult<Self> {
+        Self::from_state(&self.state()?)
+    }
+
+    fn __deepcopy__(&self, _memo: &PyAny) -> PyResult<Self> {
+        self.__copy__()
+    }
+
     #[getter]
     pub fn get_map(&self) -> PyResult<PyMap> {
         Ok(PyMap::from(&self.inner.map))
     }
 
+    #[getter]
+    pub fn get_tick(&self) -> PyResult<u64> {
+        Ok(self.inner.tick)
+    }
+
+    /// Builds a world containing exactly the items of the frame
+    #[staticmethod]
+    #[pyo3(text_signature = "(frame, map, /)")]
+    pub fn from_frame(frame: &PyFrame, map: &PyMap) -> PyResult<PyWorld> {
+        Ok(Self {
+            inner: World::from_frame(&common::Frame::from(frame), &map.inner),
+        })
+    }
+
+    #[pyo3(text_signature = "(self, filename, /)")]
+    pub fn save(&self, filename: &str) -> PyResult<()> {
+        let file = File::create(filename)
+            .map_err(|msg| PyErr::new::<PyFileNotFoundError, _>(msg.to_string()))?;
+        self.inner
+            .save(BufWriter::new(file))
+            .map_err(PyErr::new::<PyTypeError, _>)
+    }
+
+    #[staticmethod]
+    #[pyo3(text_signature = "(filename, /)")]
+    pub fn load(filename: &str) -> PyResult<PyWorld> {
+        let file = File::open(filename)
+            .map_err(|msg| PyErr::new::<PyFileNotFoundError, _>(msg.to_string()))?;
+        let world = World::restore(BufReader::new(file)).map_err(PyErr::new::<PyTypeError, _>)?;
+        Ok(Self { inner: world })
+    }
+
     pub fn update(&mut self) -> PyResult<()> {
-        self.try_get_mut()?.update();
+        self.inner.update();
         Ok(())
     }
 
     pub fn add_ant_hill(&mut self, position: &PyPose, team: u8) -> PyResult<()> {
-        self.try_get_mut()?
-            .add_ant_hill(Isometry2::from(position), team);
+        self.inner.add_ant_hill(Isometry2::from(position), team);
         Ok(())
     }
 
     pub fn try_add_ant_hill(&mut self, position: &PyPose, team: u8) -> PyResult<()> {
-        if let Some(_) = self
-            .try_get_mut()?
+        if self
+            .inner
             .try_add_ant_hill(Isometry2::from(position), team)
+            .is_some()
         {
             Ok(())
         } else {
@@ -62,9 +120,10 @@ impl PyWorld {
     }
 
     pub fn add_ant_hill_mirrored(&mut self, position: &PyPose, team: u8) -> PyResult<()> {
-        if let Some(_) = self
-            .try_get_mut()?
+        if self
+            .inner
             .add_ant_hill_mirrored(Isometry2::from(position), team)
+            .is_some()
         {
             Ok(())
         } else {
@@ -75,15 +134,15 @@ impl PyWorld {
     }
 
     pub fn add_sugar_hill(&mut self, position: &PyPose) -> PyResult<()> {
-        self.try_get_mut()?
-            .add_sugar_hill(Isometry2::from(position));
+        self.inner.add_sugar_hill(Isometry2::from(position));
         Ok(())
     }
 
     pub fn try_add_sugar_hill(&mut self, position: &PyPose) -> PyResult<()> {
-        if let Some(_) = self
-            .try_get_mut()?
+        if self
+            .inner
             .try_add_sugar_hill(Isometry2::from(position))
+            .is_some()
         {
             Ok(())
         } else {
@@ -94,9 +153,10 @@ impl PyWorld {
     }
 
     pub fn add_sugar_hill_mirrored(&mut self, position: &PyPose) -> PyResult<()> {
-        if let Some(_) = self
-            .try_get_mut()?
+        if self
+            .inner
             .add_sugar_hill_mirrored(Isometry2::from(position))
+            .is_some()
         {
             Ok(())
         } else {
@@ -106,15 +166,21 @@ impl PyWorld {
         }
     }
 
+    pub fn add_ant(&mut self, position: &PyPose, team: u8) -> PyResult<()> {
+        self.inner.add_ant(Isometry2::from(position), team);
+        Ok(())
+    }
+
     pub fn add_raspberry(&mut self, position: &PyPose) -> PyResult<()> {
-        self.try_get_mut()?.add_raspberry(Isometry2::from(position));
+        self.inner.add_raspberry(Isometry2::from(position));
         Ok(())
     }
 
     pub fn try_add_raspberry(&mut self, position: &PyPose) -> PyResult<()> {
-        if let Some(_) = self
-            .try_get_mut()?
+        if self
+            .inner
             .try_add_raspberry(Isometry2::from(position))
+            .is_some()
         {
             Ok(())
         } else {
@@ -125,9 +191,10 @@ impl PyWorld {
     }
 
     pub fn add_raspberry_mirrored(&mut self, position: &PyPose) -> PyResult<()> {
-        if let Some(_) = self
-            .try_get_mut()?
+        if self
+            .inner
             .add_raspberry_mirrored(Isometry2::from(position))
+            .is_some()
         {
             Ok(())
         } else {
@@ -137,6 +204,17 @@ impl PyWorld {
         }
     }
 
+    /// Events since the last step
+    pub fn events(&self) -> PyResult<Vec<PyEvent>> {
+        Ok(self
+            .inner
+            .events()
+            .iter()
+            .cloned()
+            .map(PyEvent::from)
+            .collect())
+    }
+
     pub fn snapshot(&self) -> PyResult<PyFrame> {
         Ok(PyFrame::from(self.inner.snapshot()))
     }
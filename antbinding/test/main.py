@@ -1,15 +1,22 @@
 #!/usr/bin/env python
 
+import copy
+import math
+import pickle
 import unittest
-from antbinding import Recording, Frame, Pose, Ant, AntHill, SugarHill
+from antbinding import (Recording, RecordingReader, RecordingWriter, Frame,
+                        Map, Pose, Vector2, Ant, AntHill, SugarHill,
+                        SmellCloud, Event, World)
 
 
 class TestRecording(unittest.TestCase):
     def test_io(self):
         filename = '/tmp/recoding.bin'
         recording = Recording()
-        recording.map.width = 35.0
-        recording.map.height = 53.0
+        map = recording.map
+        map.width = 35.0
+        map.height = 53.0
+        recording.map = map
         recording.add_frame(Frame())
         recording.dump(filename)
 
@@ -19,6 +26,331 @@ class TestRecording(unittest.TestCase):
         self.assertEqual(loaded.map.width, 35.0)
         self.assertEqual(loaded.map.height, 53.0)
 
+    def test_header(self):
+        filename = '/tmp/recoding_header.bin'
+        recording = Recording()
+        recording.seed = 7
+        recording.teams = ['red', 'blue']
+        recording.dump(filename)
+
+        loaded = Recording.load(filename)
+        self.assertEqual(loaded.seed, 7)
+        self.assertEqual(loaded.teams, ['red', 'blue'])
+        self.assertEqual(loaded.engine_version, recording.engine_version)
+        self.assertEqual(loaded.created, recording.created)
+
+    def test_load_invalid(self):
+        filename = '/tmp/recoding_invalid.bin'
+        with open(filename, 'wb') as f:
+            f.write(b'garbage')
+        with self.assertRaises(TypeError):
+            Recording.load(filename)
+
+    def test_streaming(self):
+        filename = '/tmp/recoding_streaming.bin'
+        with RecordingWriter(filename, seed=3) as writer:
+            for x in range(3):
+                frame = Frame()
+                frame.add_ant(Ant(x=float(x), id=5))
+                frame.add_anthill(AntHill(team=0, id=6))
+                writer.write(frame)
+            writer.finish(winner=1, scores=[0.0, 2.0])
+
+        with RecordingReader(filename) as reader:
+            self.assertEqual(reader.seed, 3)
+            self.assertIsNone(reader.winner)
+            frames = list(reader)
+            xs = [frame.ants[0].pose.x for frame in frames]
+            self.assertEqual(xs, [0.0, 1.0, 2.0])
+            self.assertTrue(all(frame.ants[0].id == 5 for frame in frames))
+            self.assertTrue(all(len(frame.anthills) == 1 for frame in frames))
+            self.assertEqual(reader.winner, 1)
+            self.assertEqual(reader.scores, [0.0, 2.0])
+
+        self.assertEqual(len(Recording.load(filename).frames), 3)
+
+    def test_streaming_exception(self):
+        filename = '/tmp/recoding_exception.bin'
+        with self.assertRaises(RuntimeError):
+            with RecordingWriter(filename) as writer:
+                writer.write(Frame())
+                raise RuntimeError('crashed')
+
+        # Unfinished recordings have no index at the end
+        with open(filename, 'rb') as file:
+            self.assertFalse(file.read().endswith(b'ANTIDX'))
+        with RecordingReader(filename) as reader:
+            self.assertEqual(len(list(reader)), 1)
+        with self.assertRaises(ValueError):
+            writer.finish()
+
+    def test_streaming_flush(self):
+        filename = '/tmp/recoding_flush.bin'
+        writer = RecordingWriter(filename)
+        for tick in range(5):
+            writer.write(Frame(tick=tick))
+        writer.flush()
+        writer.write(Frame(tick=5))
+
+        # Readable while the match is still running
+        with RecordingReader(filename) as reader:
+            self.assertEqual([frame.tick for frame in reader], list(range(5)))
+        writer.finish()
+        self.assertEqual(len(Recording.load(filename).frames), 6)
+
+    def test_random_access(self):
+        filename = '/tmp/recoding_random_access.bin'
+        with RecordingWriter(filename, compression=3) as writer:
+            for x in range(200):
+                frame = Frame(tick=x, time=x*0.5)
+                frame.add_ant(Ant(x=float(x), id=1))
+                writer.write(frame)
+
+        with RecordingReader(filename) as reader:
+            reader.seek_tick(120)
+            self.assertEqual(next(reader).tick, 120)
+            self.assertEqual(reader.frame_count(), 200)
+            self.assertEqual(reader.frame(150).ants[0].pose.x, 150.0)
+            xs = [frame.ants[0].pose.x for frame in reader.frames(10, 13)]
+            self.assertEqual(xs, [10.0, 11.0, 12.0])
+            with self.assertRaises(IndexError):
+                reader.frame(200)
+
+    def test_sequence(self):
+        filename = '/tmp/recoding_sequence.bin'
+        recording = Recording()
+        for tick in range(200):
+            frame = Frame(tick=tick)
+            frame.add_ant(Ant(x=float(tick), team=0, id=1))
+            frame.add_ant(Ant(x=0.0, team=1, id=2))
+            recording.add_frame(frame)
+        recording.dump(filename)
+
+        loaded = Recording.load(filename)
+        self.assertEqual(len(loaded), 200)
+        self.assertEqual(loaded[0].tick, 0)
+        self.assertEqual(loaded[130].ants[0].pose.x, 130.0)
+        self.assertEqual(loaded[-1].tick, 199)
+        self.assertEqual([f.tick for f in loaded[10:30:10]], [10, 20])
+        self.assertEqual([f.tick for f in loaded[-2:]], [198, 199])
+        with self.assertRaises(IndexError):
+            loaded[200]
+        with self.assertRaises(IndexError):
+            loaded[-201]
+        self.assertEqual(sum(1 for _ in loaded), 200)
+
+        ants = list(loaded.ants(team=1))
+        self.assertEqual(len(ants), 200)
+        tick, ant = ants[3]
+        self.assertEqual((tick, ant.id, ant.team), (3, 2, 1))
+        self.assertEqual(len(list(loaded.ants())), 400)
+
+        # Frames are still read from the file while it gets overwritten
+        loaded.add_frame(Frame(tick=200))
+        loaded.dump(filename)
+        self.assertEqual(len(Recording.load(filename)), 201)
+        lazy = Recording.load(filename)
+        lazy.dump(filename)
+        self.assertEqual(Recording.load(filename)[-1].tick, 200)
+
+    def test_events(self):
+        world = World(width=32.0, height=32.0)
+        world.add_ant_hill(Pose(x=4.0, y=4.0), 1)
+        world.add_ant(Pose(x=8.0, y=4.0), 1)
+        self.assertEqual([event.kind for event in world.events()], ['spawn'])
+
+        recording = Recording()
+        recording.add_frame(world.snapshot())
+        spawns = recording.events(kind='spawn', team=1)
+        self.assertEqual(len(spawns), 1)
+        tick, event = spawns[0]
+        self.assertEqual(tick, 0)
+        self.assertEqual(event.entities, [recording.frames[0].ants[0].id])
+        self.assertEqual(recording.events(team=0), [])
+
+    def test_frame_events(self):
+        frame = Frame(tick=4)
+        frame.add_smell_cloud(SmellCloud(x=1.0, y=2.0, team=1, code=3, id=9))
+        frame.add_event(Event('smell_emitted', entities=[9], team=1, code=3))
+        frame.add_event(Event('fight_hit', entities=[5, 6], team=0, damage=0.5))
+        frame.add_event(Event('match_end'))
+        self.assertEqual(frame.smell_clouds[0].id, 9)
+        self.assertEqual([event.kind for event in frame.events],
+                         ['smell_emitted', 'fight_hit', 'match_end'])
+        with self.assertRaises(ValueError):
+            Event('pickup', entities=[1], team=0)
+        with self.assertRaises(ValueError):
+            Event('teleport')
+
+        recording = Recording()
+        recording.add_frame(frame)
+        (tick, hit), = recording.events(kind='fight_hit')
+        self.assertEqual(tick, 4)
+        self.assertEqual(hit.entities, [5, 6])
+        self.assertEqual(hit.damage, 0.5)
+        self.assertEqual(recording.events(team=1)[0][1].code, 3)
+        self.assertIsNone(recording.events(kind='match_end')[0][1].team)
+        tick, cloud = next(recording.smell_clouds(team=1))
+        self.assertEqual(cloud.position.y, 2.0)
+
+    def test_world_save(self):
+        filename = '/tmp/world.sav'
+        world = World(width=32.0, height=24.0)
+        world.add_ant_hill(Pose(x=4.0, y=4.0, rotation=0.4), 1)
+        world.add_ant(Pose(x=8.0, y=4.0, rotation=2.1), 1)
+        world.update()
+        world.save(filename)
+
+        loaded = World.load(filename)
+        self.assertEqual(loaded.map.height, 24.0)
+        self.assertEqual(repr(loaded.snapshot().ants),
+                         repr(world.snapshot().ants))
+        self.assertEqual(repr(loaded.snapshot().anthills),
+                         repr(world.snapshot().anthills))
+
+    def test_world_from_frame(self):
+        frame = Frame(tick=3)
+        frame.add_ant(Ant(x=2.0, y=0.0, team=1, id=4))
+        frame.add_sugar_hill(SugarHill(x=4.0, y=0.0, volume=3.0, id=5))
+
+        map = Map()
+        map.width = 40.0
+        world = World.from_frame(frame, map)
+        self.assertEqual(world.tick, 3)
+        self.assertEqual(world.map.width, 40.0)
+        snapshot = world.snapshot()
+        self.assertEqual(snapshot.ants[0].id, 4)
+        self.assertAlmostEqual(snapshot.ants[0].pose.x, 2.0)
+        self.assertAlmostEqual(snapshot.sugar_hills[0].volume, 3.0)
+
+    def test_checksum(self):
+        snapshot = World().snapshot()
+        self.assertNotEqual(snapshot.checksum, 0)
+        self.assertEqual(snapshot.checksum, snapshot.state_checksum())
+
+        frame = Frame()
+        self.assertEqual(frame.checksum, 0)
+        frame.add_ant(Ant(x=2.0, y=0.0, team=1))
+        self.assertNotEqual(frame.state_checksum(), Frame().state_checksum())
+
+    def test_value_semantics(self):
+        ant = Ant(x=1.0, y=2.0)
+        ant.pose.x = 5.0
+        self.assertEqual(ant.pose.x, 1.0)
+
+        pose = ant.pose
+        pose.x = 5.0
+        self.assertEqual(ant.pose.x, 1.0)
+        ant.pose = pose
+        self.assertEqual(ant.pose.x, 5.0)
+        pose.x = 7.0
+        self.assertEqual(ant.pose.x, 5.0)
+
+        frame = Frame()
+        frame.add_ant(ant)
+        ant.hp = 0.1
+        frame.ants[0].hp = 0.2
+        frame.ants[0].pose = Pose(x=9.0)
+        self.assertEqual(frame.ants[0].hp, 1.0)
+        self.assertEqual(frame.ants[0].pose.x, 5.0)
+
+        recording = Recording()
+        recording.map.width = 1.0
+        self.assertEqual(recording.map.width, 128.0)
+
+    def test_pickle(self):
+        frame = Frame(tick=2, time=0.2)
+        frame.add_ant(Ant(x=2.0, y=4.0, team=1, id=3))
+        frame.add_sugar_hill(SugarHill(x=3.0, volume=8.0))
+        objects = [Pose(x=1.0, y=2.0, rotation=0.5), Vector2(x=1.0, y=2.0),
+                   Map(), Ant(x=2.0, team=1, hp=0.5), AntHill(x=1.0, team=1),
+                   SugarHill(volume=2.0), SmellCloud(code=3)]
+        for value in objects:
+            self.assertEqual(repr(pickle.loads(pickle.dumps(value))),
+                             repr(value))
+            self.assertEqual(repr(copy.copy(value)), repr(value))
+            self.assertEqual(repr(copy.deepcopy(value)), repr(value))
+        for copied in [pickle.loads(pickle.dumps(frame)), copy.copy(frame)]:
+            self.assertEqual(copied.tick, 2)
+            self.assertEqual(repr(copied.ants), repr(frame.ants))
+            self.assertEqual(repr(copied.sugar_hills), repr(frame.sugar_hills))
+
+        recording = Recording()
+        recording.seed = 4
+        recording.add_frame(frame)
+        loaded = pickle.loads(pickle.dumps(recording))
+        self.assertEqual(loaded.seed, 4)
+        self.assertEqual(repr(loaded[0].ants), repr(frame.ants))
+
+    def test_copy_is_independent(self):
+        pose = Pose(x=1.0)
+        copied = copy.copy(pose)
+        copied.x = 5.0
+        self.assertEqual(pose.x, 1.0)
+
+        world = World()
+        world.add_ant(Pose(x=8.0, y=4.0), 1)
+        copied = copy.deepcopy(world)
+        copied.add_ant(Pose(x=16.0, y=4.0), 1)
+        self.assertEqual(len(world.snapshot().ants), 1)
+        self.assertEqual(len(copied.snapshot().ants), 2)
+
+    def test_world_pickle(self):
+        world = World(width=32.0, height=24.0)
+        world.add_ant_hill(Pose(x=4.0, y=4.0, rotation=0.4), 1)
+        world.add_ant(Pose(x=8.0, y=4.0, rotation=2.1), 1)
+        world.update()
+
+        loaded = pickle.loads(pickle.dumps(world))
+        self.assertEqual(loaded.map.height, 24.0)
+        self.assertEqual(repr(loaded.snapshot().ants),
+                         repr(world.snapshot().ants))
+
+    def test_vector_math(self):
+        a = Vector2(x=3.0, y=4.0)
+        b = Vector2(x=1.0, y=-1.0)
+        self.assertEqual(a + b, Vector2(x=4.0, y=3.0))
+        self.assertEqual(a - b, Vector2(x=2.0, y=5.0))
+        self.assertEqual(a * 2.0, 2.0 * a)
+        self.assertEqual(-a, Vector2(x=-3.0, y=-4.0))
+        self.assertNotEqual(a, b)
+        self.assertEqual(a.dot(b), -1.0)
+        self.assertEqual(a.length(), 5.0)
+        self.assertTrue(a.normalize().approx_eq(Vector2(x=0.6, y=0.8)))
+        self.assertTrue(Vector2(x=1.0).rotate(math.pi / 2)
+                        .approx_eq(Vector2(y=1.0)))
+        self.assertAlmostEqual(Vector2(x=1.0).angle_to(Vector2(y=-2.0)),
+                               -math.pi / 2, places=5)
+
+    def test_pose_math(self):
+        pose = Pose(x=1.0, y=2.0, rotation=math.pi / 2)
+        point = Vector2(x=1.0, y=3.0)
+        local = pose.to_local(point)
+        self.assertTrue(local.approx_eq(Vector2(x=1.0), 1e-5))
+        self.assertTrue(pose.to_world(local).approx_eq(point, 1e-5))
+        self.assertAlmostEqual(pose.angle_to(point), 0.0, places=5)
+
+        other = Pose(x=-2.0, y=0.5, rotation=2.0)
+        self.assertTrue((pose * pose.inverse()).approx_eq(Pose(), 1e-5))
+        self.assertTrue((pose * other).to_world(point).approx_eq(
+            pose.to_world(other.to_world(point)), 1e-5))
+        self.assertEqual(pose.compose(other), pose * other)
+        self.assertTrue(Pose(rotation=math.pi).approx_eq(
+            Pose(rotation=-math.pi)))
+        self.assertNotEqual(pose, other)
+
+    def test_world_rotations(self):
+        world = World(width=32.0, height=32.0)
+        world.add_ant(Pose(x=8.0, y=4.0, rotation=2.1), 1)
+        world.add_ant_hill_mirrored(Pose(x=8.0, y=-6.0, rotation=0.5), 0)
+        world.update()
+        snapshot = world.snapshot()
+        self.assertTrue(snapshot.ants[0].pose.approx_eq(
+            Pose(x=8.0, y=4.0, rotation=2.1), 1e-5))
+        poses = [anthill.pose for anthill in snapshot.anthills]
+        mirrored = Pose(x=-8.0, y=6.0, rotation=0.5 - math.pi)
+        self.assertTrue(any(pose.approx_eq(mirrored, 1e-5) for pose in poses))
+
     def test_frame(self):
         frame = Frame()
 
@@ -0,0 +1,23 @@
+use crate::io::{self, Format};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    /// Format of the input (bin, json or msgpack), guessed from the extension by default
+    #[structopt(long)]
+    from: Option<Format>,
+    /// Format of the output (bin, json or msgpack), guessed from the extension by default
+    #[structopt(long)]
+    to: Option<Format>,
+    /// zstd level for compressing bincode output
+    #[structopt(long)]
+    compression: Option<i32>,
+}
+
+pub fn run(args: Args) -> Result<(), String> {
+    let recording = io::load(&args.input, args.from)?;
+    io::dump(&recording, &args.output, args.to, args.compression)
+}
@@ -0,0 +1,121 @@
+use crate::io;
+use common::diff::{Difference, EntityValue, RecordingDiff};
+use common::Team;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Args {
+    first: PathBuf,
+    second: PathBuf,
+    /// Also lists every difference of every tick
+    #[structopt(long)]
+    ticks: bool,
+}
+
+fn team_name(team: Option<Team>) -> String {
+    team.map_or("none".to_owned(), |team| team.to_string())
+}
+
+fn value_text(value: &EntityValue) -> String {
+    match value {
+        EntityValue::Ant(ant) => format!("{:?}", ant),
+        EntityValue::AntHill(anthill) => format!("{:?}", anthill),
+        EntityValue::SugarHill(sugar_hill) => format!("{:?}", sugar_hill),
+        EntityValue::SmellCloud(smell_cloud) => format!("{:?}", smell_cloud),
+        EntityValue::Raspberries(raspberries) => format!("{:?}", raspberries),
+    }
+}
+
+/// e.g. "ant 3: Ant { .. } -> Ant { .. }" or "anthill 5 removed: AntHill { .. }"
+pub fn describe(difference: &Difference) -> String {
+    let id = difference.id.map_or(String::new(), |id| format!(" {}", id));
+    match (&difference.before, &difference.after) {
+        (Some(before), Some(after)) => format!(
+            "{}{}: {} -> {}",
+            difference.kind,
+            id,
+            value_text(before),
+            value_text(after)
+        ),
+        (Some(before), None) => {
+            format!("{}{} removed: {}", difference.kind, id, value_text(before))
+        }
+        (None, Some(after)) => format!("{}{} added: {}", difference.kind, id, value_text(after)),
+        (None, None) => format!("{}{}", difference.kind, id),
+    }
+}
+
+pub fn run(args: Args) -> Result<(), String> {
+    let first = io::load(&args.first, None)?;
+    let second = io::load(&args.second, None)?;
+    let diff = RecordingDiff::between(&first, &second);
+
+    match diff.first_divergent_tick {
+        Some(tick) => println!("First divergent tick: {}", tick),
+        None => println!("Recordings are identical"),
+    }
+    println!(
+        "Frames: {} and {}, {} ticks only in the first, {} only in the second",
+        first.frames.len(),
+        second.frames.len(),
+        diff.only_first.len(),
+        diff.only_second.len()
+    );
+    if diff.winners.0 != diff.winners.1 {
+        println!(
+            "Winner: {} -> {}",
+            team_name(diff.winners.0),
+            team_name(diff.winners.1)
+        );
+    }
+    for (team, delta) in diff.score_deltas.iter().enumerate() {
+        if *delta != 0. {
+            println!("Score of team {}: {:+}", team, delta);
+        }
+    }
+    if !diff.entities.is_empty() {
+        println!("Entities (ticks with differences, max offset):");
+        for summary in diff.entities.iter() {
+            let id = summary.id.map_or(String::new(), |id| format!(" {}", id));
+            println!(
+                "  {}{}: ticks {} to {} ({}), max offset {}",
+                summary.kind,
+                id,
+                summary.first_tick,
+                summary.last_tick,
+                summary.ticks,
+                summary.max_offset
+            );
+        }
+    }
+    if args.ticks {
+        for (tick, differences) in diff.ticks.iter() {
+            println!("Tick {}:", tick);
+            for difference in differences.iter() {
+                println!("  {}", describe(difference));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+    use common::test_util::ant;
+
+    #[test]
+    fn descriptions() {
+        let mut difference = Difference {
+            kind: "ant",
+            id: Some(3),
+            before: None,
+            after: Some(EntityValue::Ant(ant(3, 0, 1.))),
+            offset: 0.,
+        };
+        assert!(describe(&difference).starts_with("ant 3 added: Ant { id: 3,"));
+        difference.before = Some(EntityValue::Ant(ant(3, 0, 0.)));
+        assert!(describe(&difference).contains("} -> Ant {"));
+    }
+}
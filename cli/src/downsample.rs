@@ -0,0 +1,20 @@
+use crate::io;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    /// Keeps every nth frame
+    #[structopt(long)]
+    every: usize,
+    /// zstd level for compressing bincode output
+    #[structopt(long)]
+    compression: Option<i32>,
+}
+
+pub fn run(args: Args) -> Result<(), String> {
+    let recording = io::load(&args.input, None)?.downsample(args.every);
+    io::dump(&recording, &args.output, None, args.compression)
+}
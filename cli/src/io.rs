@@ -0,0 +1,181 @@
+use common::recording::{RecordingReader, RecordingWriter};
+use common::{Frame, Header, Map, Recording};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Bincode,
+    Json,
+    MessagePack,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "bin" | "bincode" => Ok(Format::Bincode),
+            "json" => Ok(Format::Json),
+            "msgpack" | "messagepack" => Ok(Format::MessagePack),
+            _ => Err(format!(
+                "Unknown format {} (expected bin, json or msgpack)",
+                name
+            )),
+        }
+    }
+}
+
+impl Format {
+    /// Guesses the format from the file extension, defaulting to bincode.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Format::Json,
+            Some("msgpack") | Some("mp") => Format::MessagePack,
+            _ => Format::Bincode,
+        }
+    }
+}
+
+pub fn load(path: &Path, format: Option<Format>) -> Result<Recording, String> {
+    let file = File::open(path).map_err(|msg| format!("{}: {}", path.display(), msg))?;
+    let reader = BufReader::new(file);
+    match format.unwrap_or_else(|| Format::from_path(path)) {
+        Format::Bincode => Recording::load(reader),
+        Format::Json => Recording::from_json(reader),
+        Format::MessagePack => Recording::from_msgpack(reader),
+    }
+    .map_err(|msg| format!("{}: {}", path.display(), msg))
+}
+
+pub type Frames = Box<dyn Iterator<Item = Result<Frame, String>>>;
+
+/// Header, map and frames of a recording. Bincode recordings are decoded
+/// block by block while iterating, other formats are loaded completely.
+pub fn open(path: &Path, format: Option<Format>) -> Result<(Header, Map, Frames), String> {
+    let format = format.unwrap_or_else(|| Format::from_path(path));
+    if format != Format::Bincode {
+        let recording = load(path, Some(format))?;
+        return Ok((
+            recording.header,
+            recording.map,
+            Box::new(recording.frames.into_iter().map(Ok)),
+        ));
+    }
+    let file = File::open(path).map_err(|msg| format!("{}: {}", path.display(), msg))?;
+    let reader = RecordingReader::new(BufReader::new(file))
+        .map_err(|msg| format!("{}: {}", path.display(), msg))?;
+    let name = path.display().to_string();
+    let header = reader.header.clone();
+    let map = reader.map.clone();
+    let frames = reader.map(move |frame| frame.map_err(|msg| format!("{}: {}", name, msg)));
+    Ok((header, map, Box::new(frames)))
+}
+
+pub fn dump(
+    recording: &Recording,
+    path: &Path,
+    format: Option<Format>,
+    compression: Option<i32>,
+) -> Result<(), String> {
+    let file = File::create(path).map_err(|msg| format!("{}: {}", path.display(), msg))?;
+    let writer = BufWriter::new(file);
+    match format.unwrap_or_else(|| Format::from_path(path)) {
+        Format::Bincode => {
+            let mut writer = RecordingWriter::new(writer, &recording.header, &recording.map)?;
+            writer.set_compression(compression)?;
+            for frame in recording.frames.iter() {
+                writer.write_frame(frame)?;
+            }
+            writer.finish(recording.header.outcome.clone())?;
+            Ok(())
+        }
+        Format::Json => recording.to_json(writer),
+        Format::MessagePack => recording.to_msgpack(writer),
+    }
+    .map_err(|msg| format!("{}: {}", path.display(), msg))
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+    use common::test_util::ant;
+    use common::Frame;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ant-cli-{}-{}", std::process::id(), name))
+    }
+
+    fn recording() -> Recording {
+        let mut recording = Recording::new();
+        recording.header.seed = Some(5);
+        recording.frames = (0..3)
+            .map(|tick| Frame {
+                tick,
+                ants: vec![ant(1, 0, tick as f32)],
+                ..Frame::default()
+            })
+            .collect();
+        recording
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(Format::from_path(Path::new("a.json")), Format::Json);
+        assert_eq!(
+            Format::from_path(Path::new("a.msgpack")),
+            Format::MessagePack
+        );
+        assert_eq!(Format::from_path(Path::new("a.mp")), Format::MessagePack);
+        assert_eq!(Format::from_path(Path::new("a.rec")), Format::Bincode);
+        assert_eq!(Format::from_path(Path::new("a")), Format::Bincode);
+        assert_eq!("msgpack".parse::<Format>(), Ok(Format::MessagePack));
+        assert!("yaml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let recording = recording();
+        for (name, compression) in [
+            ("round_trip.bin", None),
+            ("round_trip_compressed.bin", Some(3)),
+            ("round_trip.json", None),
+            ("round_trip.msgpack", None),
+        ] {
+            let path = temp_path(name);
+            dump(&recording, &path, None, compression).unwrap();
+            let loaded = load(&path, None).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(loaded.header.seed, Some(5), "{}", name);
+            assert_eq!(loaded.frames, recording.frames, "{}", name);
+        }
+    }
+
+    #[test]
+    fn open_streams() {
+        let recording = recording();
+        for name in ["open_streams.bin", "open_streams.json"] {
+            let path = temp_path(name);
+            dump(&recording, &path, None, None).unwrap();
+            let (header, _, frames) = open(&path, None).unwrap();
+            let frames = frames.collect::<Result<Vec<_>, _>>();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(header.seed, Some(5), "{}", name);
+            assert_eq!(frames.unwrap(), recording.frames, "{}", name);
+        }
+    }
+
+    #[test]
+    fn explicit_format() {
+        let recording = recording();
+        let path = temp_path("explicit_format.rec");
+        dump(&recording, &path, Some(Format::Json), None).unwrap();
+        assert!(load(&path, None).is_err());
+        let loaded = load(&path, Some(Format::Json));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().frames, recording.frames);
+    }
+}
@@ -0,0 +1,45 @@
+use structopt::StructOpt;
+
+mod convert;
+mod diff;
+mod downsample;
+mod io;
+mod merge;
+mod stats;
+mod trim;
+mod verify;
+
+#[derive(StructOpt)]
+#[structopt(name = "ant", about = "Tools for ant challenge recordings")]
+enum Command {
+    /// Converts recordings between bincode, JSON and MessagePack
+    Convert(convert::Args),
+    /// Checks frame checksums, or finds the first tick at which two runs differ
+    Verify(verify::Args),
+    /// Computes per team time series as CSV or JSON
+    Stats(stats::Args),
+    /// Compares two recordings entity by entity
+    Diff(diff::Args),
+    /// Cuts out a tick range, starting again at tick 0
+    Trim(trim::Args),
+    /// Keeps only every nth frame
+    Downsample(downsample::Args),
+    /// Concatenates recordings of the same map and teams
+    Merge(merge::Args),
+}
+
+fn main() {
+    let result = match Command::from_args() {
+        Command::Convert(args) => convert::run(args),
+        Command::Verify(args) => verify::run(args),
+        Command::Stats(args) => stats::run(args),
+        Command::Diff(args) => diff::run(args),
+        Command::Trim(args) => trim::run(args),
+        Command::Downsample(args) => downsample::run(args),
+        Command::Merge(args) => merge::run(args),
+    };
+    if let Err(msg) = result {
+        eprintln!("{}", msg);
+        std::process::exit(1);
+    }
+}
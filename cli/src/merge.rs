@@ -0,0 +1,85 @@
+use crate::io;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Args {
+    /// Recordings of the same map and teams, in playback order
+    #[structopt(required = true)]
+    inputs: Vec<PathBuf>,
+    #[structopt(short, long)]
+    output: PathBuf,
+    /// zstd level for compressing bincode output
+    #[structopt(long)]
+    compression: Option<i32>,
+}
+
+pub fn run(args: Args) -> Result<(), String> {
+    let mut inputs = args.inputs.iter();
+    let mut recording = io::load(inputs.next().expect("required by structopt"), None)?;
+    for path in inputs {
+        recording
+            .append(io::load(path, None)?)
+            .map_err(|msg| format!("{}: {}", path.display(), msg))?;
+    }
+    io::dump(&recording, &args.output, None, args.compression)
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+    use common::{Frame, Map, Recording};
+
+    #[test]
+    fn merged() {
+        let directory = std::env::temp_dir();
+        let path = |name: &str| directory.join(format!("ant-cli-{}-{}", std::process::id(), name));
+        let mut first = Recording::new();
+        first.frames = vec![
+            Frame::default(),
+            Frame {
+                tick: 1,
+                ..Frame::default()
+            },
+        ];
+        let mut second = Recording::new();
+        second.frames = vec![Frame::default()];
+        let mut other_map = Recording::new();
+        other_map.map = Map {
+            width: 1.,
+            height: 1.,
+        };
+        for (name, recording) in [
+            ("first.bin", &first),
+            ("second.json", &second),
+            ("other_map.bin", &other_map),
+        ] {
+            io::dump(recording, &path(name), None, None).unwrap();
+        }
+
+        let merged = run(Args {
+            inputs: vec![path("first.bin"), path("second.json")],
+            output: path("merged.bin"),
+            compression: Some(3),
+        })
+        .and_then(|_| io::load(&path("merged.bin"), None));
+        let error = run(Args {
+            inputs: vec![path("first.bin"), path("other_map.bin")],
+            output: path("merged.bin"),
+            compression: None,
+        })
+        .unwrap_err();
+        for name in ["first.bin", "second.json", "other_map.bin", "merged.bin"] {
+            std::fs::remove_file(path(name)).unwrap();
+        }
+
+        let ticks = merged
+            .unwrap()
+            .frames
+            .iter()
+            .map(|frame| frame.tick)
+            .collect::<Vec<_>>();
+        assert_eq!(ticks, vec![0, 1, 2]);
+        assert!(error.starts_with(&path("other_map.bin").display().to_string()));
+    }
+}
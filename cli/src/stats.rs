@@ -0,0 +1,389 @@
+use crate::io;
+use common::{EntityId, Event, Frame, Map, Team};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Args {
+    recording: PathBuf,
+    /// Written to stdout by default
+    #[structopt(short, long)]
+    output: Option<PathBuf>,
+    /// csv or json, guessed from the extension of the output by default
+    #[structopt(long)]
+    format: Option<OutputFormat>,
+    /// Number of grid cells per axis used to estimate the territory
+    #[structopt(long, default_value = "32")]
+    resolution: usize,
+    /// Minimum number of ticks between territory estimates. Frames in
+    /// between repeat the last estimate.
+    #[structopt(long, default_value = "10")]
+    territory_every: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown format {} (expected csv or json)", name)),
+        }
+    }
+}
+
+impl OutputFormat {
+    fn from_path(path: Option<&Path>) -> Self {
+        match path
+            .and_then(|path| path.extension())
+            .and_then(|extension| extension.to_str())
+        {
+            Some("json") => OutputFormat::Json,
+            _ => OutputFormat::Csv,
+        }
+    }
+}
+
+/// State of one team at one frame. Counters marked as totals are summed
+/// up since the start of the recording.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct TeamStats {
+    pub tick: u64,
+    pub time: f32,
+    pub team: Team,
+    pub live_ants: usize,
+    pub mean_hp: f32, // 0 without ants
+    pub sugar_carried: usize,
+    pub sugar_delivered: usize,      // total
+    pub distance: f32,               // total travelled by all ants
+    pub smell_clouds_emitted: usize, // total
+    pub territory: f32,              // share of the map closest to the team's ants
+}
+
+const CSV_HEADER: &str = "tick,time,team,live_ants,mean_hp,sugar_carried,sugar_delivered,distance,smell_clouds_emitted,territory";
+
+pub fn run(args: Args) -> Result<(), String> {
+    let (header, map, frames) = io::open(&args.recording, None)?;
+    let mut collector = Collector::new(
+        &map,
+        header.teams.len(),
+        args.resolution,
+        args.territory_every,
+    );
+    for frame in frames {
+        collector.add(&frame?);
+    }
+    let stats = collector.finish();
+    let format = args
+        .format
+        .unwrap_or_else(|| OutputFormat::from_path(args.output.as_deref()));
+    match &args.output {
+        Some(path) => {
+            let file = File::create(path).map_err(|msg| format!("{}: {}", path.display(), msg))?;
+            write(&stats, format, BufWriter::new(file))
+                .map_err(|msg| format!("{}: {}", path.display(), msg))
+        }
+        None => write(&stats, format, std::io::stdout().lock()),
+    }
+}
+
+fn write<W: Write>(stats: &[TeamStats], format: OutputFormat, mut writer: W) -> Result<(), String> {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer(&mut writer, stats).map_err(|msg| msg.to_string())?
+        }
+        OutputFormat::Csv => {
+            writeln!(writer, "{}", CSV_HEADER).map_err(|msg| msg.to_string())?;
+            for row in stats.iter() {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{},{},{},{}",
+                    row.tick,
+                    row.time,
+                    row.team,
+                    row.live_ants,
+                    row.mean_hp,
+                    row.sugar_carried,
+                    row.sugar_delivered,
+                    row.distance,
+                    row.smell_clouds_emitted,
+                    row.territory
+                )
+                .map_err(|msg| msg.to_string())?;
+            }
+        }
+    }
+    writer.flush().map_err(|msg| msg.to_string())
+}
+
+/// Share of the map per team, sampled at the centers of a grid over the
+/// map, which is centered at the origin.
+/// Each cell belongs to the team of the nearest ant.
+fn territory(frame: &Frame, width: f32, height: f32, resolution: usize, teams: usize) -> Vec<f32> {
+    if frame.ants.is_empty() || resolution == 0 {
+        return vec![0.; teams];
+    }
+    let mut cells = vec![0usize; teams];
+    for row in 0..resolution {
+        let y = -height / 2. + (row as f32 + 0.5) * height / resolution as f32;
+        for column in 0..resolution {
+            let x = -width / 2. + (column as f32 + 0.5) * width / resolution as f32;
+            let nearest = frame
+                .ants
+                .iter()
+                .map(|ant| {
+                    let (dx, dy) = (ant.pose.x - x, ant.pose.y - y);
+                    (dx * dx + dy * dy, ant.team)
+                })
+                .fold((f32::INFINITY, 0), |best, candidate| {
+                    if candidate.0 < best.0 {
+                        candidate
+                    } else {
+                        best
+                    }
+                });
+            cells[nearest.1 as usize] += 1;
+        }
+    }
+    let total = (resolution * resolution) as f32;
+    cells
+        .into_iter()
+        .map(|count| count as f32 / total)
+        .collect()
+}
+
+/// Computes the rows frame by frame, so that recordings can be streamed.
+pub struct Collector {
+    width: f32,
+    height: f32,
+    resolution: usize,
+    territory_every: u64,
+    teams: usize, // named in the header or seen so far
+    totals: Vec<TeamStats>,
+    carrying: HashSet<EntityId>,
+    positions: HashMap<EntityId, (f32, f32)>,
+    territory: Vec<f32>,
+    territory_tick: Option<u64>,
+    frames: Vec<(u64, f32, Vec<TeamStats>)>, // rows of the teams seen until then
+}
+
+impl Collector {
+    pub fn new(map: &Map, teams: usize, resolution: usize, territory_every: u64) -> Self {
+        Collector {
+            width: map.width,
+            height: map.height,
+            resolution,
+            territory_every,
+            teams,
+            totals: vec![TeamStats::default(); teams],
+            carrying: HashSet::new(),
+            positions: HashMap::new(),
+            territory: vec![],
+            territory_tick: None,
+            frames: vec![],
+        }
+    }
+
+    fn see_team(&mut self, team: Team) {
+        self.teams = self.teams.max(team as usize + 1);
+        if self.totals.len() < self.teams {
+            self.totals.resize(self.teams, TeamStats::default());
+        }
+    }
+
+    pub fn add(&mut self, frame: &Frame) {
+        frame
+            .ants
+            .iter()
+            .map(|ant| ant.team)
+            .chain(frame.anthills.iter().map(|anthill| anthill.team))
+            .chain(frame.smells_clouds.iter().map(|cloud| cloud.team))
+            .chain(frame.events.iter().filter_map(Event::team))
+            .for_each(|team| self.see_team(team));
+
+        for event in frame.events.iter() {
+            match event {
+                Event::Pickup { ant, .. } => {
+                    self.carrying.insert(*ant);
+                }
+                Event::Delivery { ant, team, .. } => {
+                    self.carrying.remove(ant);
+                    self.totals[*team as usize].sugar_delivered += 1;
+                }
+                Event::Death { ant, .. } => {
+                    self.carrying.remove(ant);
+                }
+                Event::SmellEmitted { team, .. } => {
+                    self.totals[*team as usize].smell_clouds_emitted += 1;
+                }
+                _ => {}
+            }
+        }
+
+        let mut rows = vec![TeamStats::default(); self.teams];
+        let mut next_positions = HashMap::with_capacity(frame.ants.len());
+        for ant in frame.ants.iter() {
+            let team = ant.team as usize;
+            rows[team].live_ants += 1;
+            rows[team].mean_hp += ant.hp;
+            if self.carrying.contains(&ant.id) {
+                rows[team].sugar_carried += 1;
+            }
+            if let Some((x, y)) = self.positions.get(&ant.id) {
+                self.totals[team].distance += (ant.pose.x - x).hypot(ant.pose.y - y);
+            }
+            next_positions.insert(ant.id, (ant.pose.x, ant.pose.y));
+        }
+        self.positions = next_positions;
+
+        let due = self
+            .territory_tick
+            .is_none_or(|tick| frame.tick >= tick + self.territory_every);
+        if due {
+            self.territory = territory(frame, self.width, self.height, self.resolution, self.teams);
+            self.territory_tick = Some(frame.tick);
+        }
+        for (team, row) in rows.iter_mut().enumerate() {
+            row.tick = frame.tick;
+            row.time = frame.time;
+            row.team = team as Team;
+            if row.live_ants > 0 {
+                row.mean_hp /= row.live_ants as f32;
+            }
+            row.sugar_delivered = self.totals[team].sugar_delivered;
+            row.distance = self.totals[team].distance;
+            row.smell_clouds_emitted = self.totals[team].smell_clouds_emitted;
+            row.territory = self.territory.get(team).copied().unwrap_or(0.);
+        }
+        self.frames.push((frame.tick, frame.time, rows));
+    }
+
+    /// One row per frame and team, ordered by frame. Teams first seen in
+    /// later frames get empty rows in the frames before.
+    pub fn finish(self) -> Vec<TeamStats> {
+        let teams = self.teams;
+        let mut stats = Vec::with_capacity(self.frames.len() * teams);
+        for (tick, time, mut rows) in self.frames.into_iter() {
+            for team in rows.len()..teams {
+                rows.push(TeamStats {
+                    tick,
+                    time,
+                    team: team as Team,
+                    ..TeamStats::default()
+                });
+            }
+            stats.extend(rows);
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+    use common::test_util::ant;
+    use common::Ant;
+
+    const MAP: Map = Map {
+        width: 4.,
+        height: 2.,
+    };
+
+    fn collect(frames: &[Frame], territory_every: u64) -> Vec<TeamStats> {
+        let mut collector = Collector::new(&MAP, 0, 4, territory_every);
+        frames.iter().for_each(|frame| collector.add(frame));
+        collector.finish()
+    }
+
+    #[test]
+    fn team_series() {
+        let first = Frame {
+            ants: vec![
+                Ant {
+                    hp: 0.,
+                    ..ant(1, 0, -2.)
+                },
+                ant(2, 0, -1.),
+                ant(3, 1, 2.),
+            ],
+            events: vec![Event::Pickup {
+                ant: 2,
+                team: 0,
+                sugar_hill: 9,
+            }],
+            ..Frame::default()
+        };
+        let second = Frame {
+            tick: 1,
+            ants: vec![ant(1, 0, -2.), ant(2, 0, 0.), ant(3, 1, 2.)],
+            events: vec![
+                Event::Delivery {
+                    ant: 2,
+                    team: 0,
+                    anthill: 8,
+                },
+                Event::SmellEmitted {
+                    smell_cloud: 7,
+                    team: 1,
+                    code: 0,
+                },
+            ],
+            ..Frame::default()
+        };
+
+        let stats = collect(&[first, second], 1);
+        assert_eq!(stats.len(), 4);
+        let (first, last) = (&stats[0], &stats[2]);
+        assert_eq!((first.team, first.live_ants, first.mean_hp), (0, 2, 0.5));
+        assert_eq!((first.sugar_carried, first.sugar_delivered), (1, 0));
+        assert_eq!((last.sugar_carried, last.sugar_delivered), (0, 1));
+        assert_eq!(last.distance, 1.);
+        assert_eq!(stats[3].smell_clouds_emitted, 1);
+        assert_eq!(stats[3].mean_hp, 1.);
+        // Columns at x = -1.5, -0.5, 0.5 belong to team 0 in the last frame
+        assert_eq!((last.territory, stats[3].territory), (0.75, 0.25));
+    }
+
+    #[test]
+    fn sampled_territory() {
+        let frames = (0..3)
+            .map(|tick| {
+                let mut ants = vec![ant(1, 0, -1.)];
+                // Team 1 joins at tick 1
+                if tick > 0 {
+                    ants.push(ant(2, 1, 1.));
+                }
+                Frame {
+                    tick,
+                    ants,
+                    ..Frame::default()
+                }
+            })
+            .collect::<Vec<_>>();
+        let stats = collect(&frames, 2);
+        assert_eq!(stats.len(), 6);
+        assert_eq!(
+            stats[1],
+            TeamStats {
+                team: 1,
+                ..TeamStats::default()
+            }
+        );
+        // Tick 1 repeats the estimate of tick 0
+        assert_eq!((stats[2].territory, stats[3].territory), (1., 0.));
+        assert_eq!(stats[3].live_ants, 1);
+        assert_eq!((stats[4].territory, stats[5].territory), (0.5, 0.5));
+    }
+}
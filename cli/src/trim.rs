@@ -0,0 +1,23 @@
+use crate::io;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    /// First tick to keep
+    #[structopt(long, default_value = "0")]
+    from: u64,
+    /// First tick to drop
+    #[structopt(long)]
+    to: Option<u64>,
+    /// zstd level for compressing bincode output
+    #[structopt(long)]
+    compression: Option<i32>,
+}
+
+pub fn run(args: Args) -> Result<(), String> {
+    let recording = io::load(&args.input, None)?.trim(args.from..args.to.unwrap_or(u64::MAX));
+    io::dump(&recording, &args.output, None, args.compression)
+}
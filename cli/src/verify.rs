@@ -0,0 +1,176 @@
+use crate::diff::describe;
+use crate::io;
+use common::diff::RecordingDiff;
+use common::{Frame, Recording};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Args {
+    recording: PathBuf,
+    /// Second run of the same match, compared tick by tick
+    #[structopt(long)]
+    against: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<(), String> {
+    let recording = io::load(&args.recording, None)?;
+    match args.against {
+        Some(path) => compare(&recording, &io::load(&path, None)?),
+        None => verify(&recording),
+    }
+}
+
+/// Stored checksum, or the hash of the state for frames without one
+fn checksum(frame: &Frame) -> u64 {
+    if frame.checksum == 0 {
+        frame.state_checksum()
+    } else {
+        frame.checksum
+    }
+}
+
+/// Checks that every stored state still has the checksum it was simulated with.
+fn verify(recording: &Recording) -> Result<(), String> {
+    let mut unchecked = 0;
+    for frame in recording.frames.iter() {
+        if frame.checksum == 0 {
+            unchecked += 1;
+        } else if frame.checksum != frame.state_checksum() {
+            return Err(format!(
+                "Tick {}: stored checksum {:016x} but the state hashes to {:016x}",
+                frame.tick,
+                frame.checksum,
+                frame.state_checksum()
+            ));
+        }
+    }
+    println!(
+        "{} frames verified, {} without checksum",
+        recording.frames.len() - unchecked,
+        unchecked
+    );
+    Ok(())
+}
+
+/// Reports the first tick at which two runs differ and the entities
+/// which changed. Frames are matched by tick as in `ant diff`, and frames
+/// of the same tick also have to agree on their checksums.
+fn compare(first: &Recording, second: &Recording) -> Result<(), String> {
+    let diff = RecordingDiff::between(first, second);
+    let checksum_tick = first
+        .frames
+        .iter()
+        .filter(|a| {
+            second
+                .frame_by_tick(a.tick)
+                .is_some_and(|b| checksum(a) != checksum(b))
+        })
+        .map(|frame| frame.tick)
+        .min();
+    let tick = match diff
+        .first_divergent_tick
+        .into_iter()
+        .chain(checksum_tick)
+        .min()
+    {
+        Some(tick) => tick,
+        None => {
+            println!("{} frames match", first.frames.len());
+            return Ok(());
+        }
+    };
+    if diff.only_first.contains(&tick) {
+        return Err(format!(
+            "Recordings diverge at tick {}, which only the first one has",
+            tick
+        ));
+    }
+    if diff.only_second.contains(&tick) {
+        return Err(format!(
+            "Recordings diverge at tick {}, which only the second one has",
+            tick
+        ));
+    }
+    if let (Some(a), Some(b)) = (first.frame_by_tick(tick), second.frame_by_tick(tick)) {
+        println!(
+            "Tick {}: checksum {:016x} != {:016x}",
+            tick,
+            checksum(a),
+            checksum(b)
+        );
+    }
+    if let Some((_, differences)) = diff.ticks.iter().find(|(other, _)| *other == tick) {
+        for difference in differences.iter() {
+            println!("  {}", describe(difference));
+        }
+    }
+    Err(format!("Recordings diverge at tick {}", tick))
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+    use common::test_util::ant;
+
+    fn run(positions: &[f32]) -> Recording {
+        let mut recording = Recording::new();
+        recording.frames = positions
+            .iter()
+            .enumerate()
+            .map(|(tick, &x)| {
+                let mut frame = Frame {
+                    tick: tick as u64,
+                    ants: vec![ant(1, 0, x)],
+                    ..Frame::default()
+                };
+                frame.checksum = frame.state_checksum();
+                frame
+            })
+            .collect();
+        recording
+    }
+
+    #[test]
+    fn checksums() {
+        let mut recording = run(&[0., 1., 2.]);
+        recording.frames[0].checksum = 0;
+        assert!(verify(&recording).is_ok());
+        recording.frames[2].ants[0].hp = 0.5;
+        assert!(verify(&recording).unwrap_err().starts_with("Tick 2:"));
+    }
+
+    #[test]
+    fn runs() {
+        let first = run(&[0., 1., 2.]);
+        assert!(compare(&first, &run(&[0., 1., 2.])).is_ok());
+
+        let error = compare(&first, &run(&[0., 1.5, 2.])).unwrap_err();
+        assert_eq!(error, "Recordings diverge at tick 1");
+
+        // Frames without checksum are compared by their state
+        let mut unchecked = run(&[0., 1., 2.]);
+        unchecked.frames[1].checksum = 0;
+        assert!(compare(&first, &unchecked).is_ok());
+
+        let error = compare(&first, &run(&[0., 1.])).unwrap_err();
+        assert_eq!(
+            error,
+            "Recordings diverge at tick 2, which only the first one has"
+        );
+
+        let mut skipped = run(&[0., 1., 2.]);
+        skipped.frames.remove(1);
+        let error = compare(&skipped, &first).unwrap_err();
+        assert_eq!(
+            error,
+            "Recordings diverge at tick 1, which only the second one has"
+        );
+
+        // States with the same entities but different stored checksums
+        let mut other_checksum = run(&[0., 1., 2.]);
+        other_checksum.frames[2].checksum += 1;
+        let error = compare(&first, &other_checksum).unwrap_err();
+        assert_eq!(error, "Recordings diverge at tick 2");
+    }
+}
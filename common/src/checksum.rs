@@ -0,0 +1,44 @@
+use serde::Serialize;
+use std::io::Write;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64 bit FNV-1a, which unlike `DefaultHasher` is the same for every
+/// build and platform.
+struct Fnv1a(u64);
+
+impl Write for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Stable hash of the bincode encoding of the value.
+/// Floats are hashed by their bit pattern.
+pub fn checksum<T: Serialize + ?Sized>(value: &T) -> u64 {
+    let mut hasher = Fnv1a(FNV_OFFSET);
+    bincode::serialize_into(&mut hasher, value).expect("hashing can't fail");
+    hasher.0
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+
+    #[test]
+    fn known_values() {
+        // FNV-1a of the raw bytes, an empty slice is just its u64 length
+        assert_eq!(checksum(&1u8), 0xaf63_bc4c_8601_b62c);
+        assert_eq!(checksum(&[0u8; 0][..]), 0xa8c7_f832_281a_39c5);
+        assert_ne!(checksum(&0.0f32), checksum(&-0.0f32));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::events::Event;
+use crate::math::{Pose, Vector2};
+use crate::objects::{Ant, AntHill, EntityId, SmellCloud, SugarHill};
+use crate::Frame;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub trait Entity {
+    fn id(&self) -> EntityId;
+    fn position(&self) -> Vector2;
+}
+
+impl Entity for Ant {
+    fn id(&self) -> EntityId {
+        self.id
+    }
+
+    fn position(&self) -> Vector2 {
+        Vector2 {
+            x: self.pose.x,
+            y: self.pose.y,
+        }
+    }
+}
+
+impl Entity for AntHill {
+    fn id(&self) -> EntityId {
+        self.id
+    }
+
+    fn position(&self) -> Vector2 {
+        Vector2 {
+            x: self.pose.x,
+            y: self.pose.y,
+        }
+    }
+}
+
+impl Entity for SugarHill {
+    fn id(&self) -> EntityId {
+        self.id
+    }
+
+    fn position(&self) -> Vector2 {
+        Vector2 {
+            x: self.pose.x,
+            y: self.pose.y,
+        }
+    }
+}
+
+impl Entity for SmellCloud {
+    fn id(&self) -> EntityId {
+        self.id
+    }
+
+    fn position(&self) -> Vector2 {
+        self.position
+    }
+}
+
+/// Changes of one kind of entities between two frames.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Changes<T> {
+    pub updated: Vec<T>, // changed or new entities
+    pub removed: Vec<EntityId>,
+}
+
+impl<T> Changes<T>
+where
+    T: Entity + Clone + PartialEq,
+{
+    pub fn between(previous: &[T], next: &[T]) -> Self {
+        let previous_by_id = previous
+            .iter()
+            .map(|entity| (entity.id(), entity))
+            .collect::<HashMap<_, _>>();
+        let next_by_id = next
+            .iter()
+            .map(|entity| (entity.id(), entity))
+            .collect::<HashMap<_, _>>();
+        Changes {
+            updated: next
+                .iter()
+                .filter(|entity| previous_by_id.get(&entity.id()) != Some(entity))
+                .cloned()
+                .collect(),
+            removed: previous
+                .iter()
+                .map(Entity::id)
+                .filter(|id| !next_by_id.contains_key(id))
+                .collect(),
+        }
+    }
+
+    /// Existing entities keep their position, new ones get appended.
+    pub fn apply(&self, entities: &mut Vec<T>) {
+        if !self.removed.is_empty() {
+            entities.retain(|entity| !self.removed.contains(&entity.id()));
+        }
+        let mut index_by_id = entities
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| (entity.id(), index))
+            .collect::<HashMap<_, _>>();
+        for update in self.updated.iter() {
+            match index_by_id.get(&update.id()) {
+                Some(&index) => entities[index] = update.clone(),
+                None => {
+                    index_by_id.insert(update.id(), entities.len());
+                    entities.push(update.clone());
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Difference between two consecutive frames keyed by entity ID.
+/// Raspberries have no ID and are only stored when any of them changed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrameDelta {
+    pub tick: u64,
+    pub time: f32,
+    pub ants: Changes<Ant>,
+    pub anthills: Changes<AntHill>,
+    pub raspberries: Option<Vec<Pose>>,
+    pub sugar_hills: Changes<SugarHill>,
+    pub smells_clouds: Changes<SmellCloud>,
+    pub events: Vec<Event>,
+    pub checksum: u64,
+}
+
+impl FrameDelta {
+    pub fn between(previous: &Frame, next: &Frame) -> Self {
+        FrameDelta {
+            tick: next.tick,
+            time: next.time,
+            ants: Changes::between(&previous.ants, &next.ants),
+            anthills: Changes::between(&previous.anthills, &next.anthills),
+            raspberries: if previous.raspberries == next.raspberries {
+                None
+            } else {
+                Some(next.raspberries.clone())
+            },
+            sugar_hills: Changes::between(&previous.sugar_hills, &next.sugar_hills),
+            smells_clouds: Changes::between(&previous.smells_clouds, &next.smells_clouds),
+            events: next.events.clone(),
+            checksum: next.checksum,
+        }
+    }
+
+    /// Reconstructs the next frame from the previous one.
+    pub fn apply(&self, previous: &Frame) -> Frame {
+        let mut frame = previous.clone();
+        frame.tick = self.tick;
+        frame.time = self.time;
+        self.ants.apply(&mut frame.ants);
+        self.anthills.apply(&mut frame.anthills);
+        if let Some(raspberries) = &self.raspberries {
+            frame.raspberries = raspberries.clone();
+        }
+        self.sugar_hills.apply(&mut frame.sugar_hills);
+        self.smells_clouds.apply(&mut frame.smells_clouds);
+        frame.events = self.events.clone();
+        frame.checksum = self.checksum;
+        frame
+    }
+
+    /// Like `between` but only if applying the delta gives exactly `next`
+    /// again. This fails e.g. for reordered entities or duplicate IDs, in
+    /// which case the frame has to be stored as a keyframe.
+    pub fn try_between(previous: &Frame, next: &Frame) -> Option<Self> {
+        let delta = Self::between(previous, next);
+        if &delta.apply(previous) == next {
+            Some(delta)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+    use crate::test_util::ant;
+
+    fn frame(ants: Vec<Ant>) -> Frame {
+        Frame {
+            tick: 0,
+            time: 0.,
+            ants,
+            anthills: vec![AntHill {
+                id: 100,
+                pose: Pose::zero(),
+                team: 0,
+            }],
+            raspberries: vec![Pose::zero()],
+            sugar_hills: vec![],
+            smells_clouds: vec![SmellCloud {
+                id: 200,
+                position: Vector2 { x: 1., y: 2. },
+                code: 3,
+                age: 0,
+                team: 0,
+            }],
+            events: vec![],
+            checksum: 0,
+        }
+    }
+
+    #[test]
+    fn only_changes_are_stored() {
+        let previous = frame(vec![ant(1, 0, 0.), ant(2, 0, 0.)]);
+        let next = frame(vec![ant(1, 0, 0.), ant(2, 0, 1.)]);
+        let delta = FrameDelta::between(&previous, &next);
+        assert_eq!(delta.ants.updated, vec![ant(2, 0, 1.)]);
+        assert!(delta.ants.removed.is_empty());
+        assert!(delta.anthills.is_empty());
+        assert!(delta.smells_clouds.is_empty());
+        assert!(delta.raspberries.is_none());
+        assert_eq!(delta.apply(&previous), next);
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let previous = frame(vec![ant(1, 0, 0.), ant(2, 0, 0.)]);
+        let next = frame(vec![ant(2, 0, 0.), ant(3, 0, 5.)]);
+        let delta = FrameDelta::try_between(&previous, &next).unwrap();
+        assert_eq!(delta.ants.removed, vec![1]);
+        assert_eq!(delta.apply(&previous), next);
+    }
+
+    #[test]
+    fn events_are_not_inherited() {
+        let mut previous = frame(vec![ant(1, 0, 0.)]);
+        previous.events.push(Event::Spawn { ant: 1, team: 0 });
+        let next = frame(vec![ant(1, 0, 0.)]);
+        let delta = FrameDelta::try_between(&previous, &next).unwrap();
+        assert!(delta.apply(&previous).events.is_empty());
+    }
+
+    #[test]
+    fn reordered_needs_keyframe() {
+        let previous = frame(vec![ant(1, 0, 0.), ant(2, 0, 0.)]);
+        let next = frame(vec![ant(2, 0, 0.), ant(1, 0, 1.)]);
+        assert!(FrameDelta::try_between(&previous, &next).is_none());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::delta::Entity;
+use crate::math::Pose;
+use crate::objects::{Ant, AntHill, EntityId, SmellCloud, SugarHill, Team};
+use crate::{Frame, Recording};
+use std::collections::{BTreeMap, HashMap};
+
+/// State of a differing entity in one of the frames
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntityValue {
+    Ant(Ant),
+    AntHill(AntHill),
+    SugarHill(SugarHill),
+    SmellCloud(SmellCloud),
+    Raspberries(Vec<Pose>),
+}
+
+impl From<Ant> for EntityValue {
+    fn from(ant: Ant) -> Self {
+        EntityValue::Ant(ant)
+    }
+}
+
+impl From<AntHill> for EntityValue {
+    fn from(anthill: AntHill) -> Self {
+        EntityValue::AntHill(anthill)
+    }
+}
+
+impl From<SugarHill> for EntityValue {
+    fn from(sugar_hill: SugarHill) -> Self {
+        EntityValue::SugarHill(sugar_hill)
+    }
+}
+
+impl From<SmellCloud> for EntityValue {
+    fn from(smell_cloud: SmellCloud) -> Self {
+        EntityValue::SmellCloud(smell_cloud)
+    }
+}
+
+/// One entity which differs between two frames, matched by ID.
+/// Raspberries have no ID and are compared as a whole.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    pub kind: &'static str,
+    pub id: Option<EntityId>,
+    pub before: Option<EntityValue>, // None if only in the second frame
+    pub after: Option<EntityValue>,  // None if only in the first frame
+    pub offset: f32,                 // distance between both positions, 0 if only in one
+}
+
+fn entity_differences<T>(kind: &'static str, before: &[T], after: &[T]) -> Vec<Difference>
+where
+    T: Entity + PartialEq + Clone + Into<EntityValue>,
+{
+    let after_by_id = after
+        .iter()
+        .map(|entity| (entity.id(), entity))
+        .collect::<HashMap<_, _>>();
+    let before_by_id = before
+        .iter()
+        .map(|entity| (entity.id(), entity))
+        .collect::<HashMap<_, _>>();
+    let changed_or_removed =
+        before
+            .iter()
+            .filter_map(|entity| match after_by_id.get(&entity.id()) {
+                Some(other) if *other == entity => None,
+                other => Some(Difference {
+                    kind,
+                    id: Some(entity.id()),
+                    before: Some(entity.clone().into()),
+                    after: other.map(|&other| other.clone().into()),
+                    offset: other.map_or(0., |other| entity.position().distance(other.position())),
+                }),
+            });
+    let added = after
+        .iter()
+        .filter(|entity| !before_by_id.contains_key(&entity.id()))
+        .map(|entity| Difference {
+            kind,
+            id: Some(entity.id()),
+            before: None,
+            after: Some(entity.clone().into()),
+            offset: 0.,
+        });
+    changed_or_removed.chain(added).collect()
+}
+
+/// All entities which differ between the frames. Tick, time, events and
+/// checksums are not compared.
+pub fn frame_differences(before: &Frame, after: &Frame) -> Vec<Difference> {
+    let mut differences = entity_differences("ant", &before.ants, &after.ants);
+    differences.extend(entity_differences(
+        "anthill",
+        &before.anthills,
+        &after.anthills,
+    ));
+    differences.extend(entity_differences(
+        "sugar_hill",
+        &before.sugar_hills,
+        &after.sugar_hills,
+    ));
+    differences.extend(entity_differences(
+        "smell_cloud",
+        &before.smells_clouds,
+        &after.smells_clouds,
+    ));
+    if before.raspberries != after.raspberries {
+        differences.push(Difference {
+            kind: "raspberries",
+            id: None,
+            before: Some(EntityValue::Raspberries(before.raspberries.clone())),
+            after: Some(EntityValue::Raspberries(after.raspberries.clone())),
+            offset: 0.,
+        });
+    }
+    differences
+}
+
+/// How one entity differed over the whole recording
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntitySummary {
+    pub kind: &'static str,
+    pub id: Option<EntityId>,
+    pub first_tick: u64,
+    pub last_tick: u64,
+    pub ticks: usize, // number of ticks with differences
+    pub max_offset: f32,
+}
+
+/// Comparison of two recordings, e.g. of the same match before and after
+/// changing the engine or an agent. Frames are matched by tick and
+/// entities by ID.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordingDiff {
+    pub first_divergent_tick: Option<u64>,
+    pub ticks: Vec<(u64, Vec<Difference>)>, // only ticks with differences
+    pub only_first: Vec<u64>,               // ticks missing in the second recording
+    pub only_second: Vec<u64>,              // ticks missing in the first recording
+    pub entities: Vec<EntitySummary>,       // ordered by first differing tick
+    pub winners: (Option<Team>, Option<Team>),
+    pub score_deltas: Vec<f32>, // second minus first, indexed by team
+}
+
+impl RecordingDiff {
+    pub fn between(first: &Recording, second: &Recording) -> Self {
+        let mut ticks = Vec::new();
+        let mut only_first = Vec::new();
+        let mut only_second = Vec::new();
+        let mut a = first.frames.iter().peekable();
+        let mut b = second.frames.iter().peekable();
+        loop {
+            match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) if x.tick == y.tick => {
+                    let differences = frame_differences(x, y);
+                    if !differences.is_empty() {
+                        ticks.push((x.tick, differences));
+                    }
+                    a.next();
+                    b.next();
+                }
+                (Some(x), Some(y)) if x.tick < y.tick => {
+                    only_first.push(x.tick);
+                    a.next();
+                }
+                (Some(_), Some(y)) => {
+                    only_second.push(y.tick);
+                    b.next();
+                }
+                (Some(x), None) => {
+                    only_first.push(x.tick);
+                    a.next();
+                }
+                (None, Some(y)) => {
+                    only_second.push(y.tick);
+                    b.next();
+                }
+                (None, None) => break,
+            }
+        }
+
+        let first_divergent_tick = ticks
+            .first()
+            .map(|(tick, _)| *tick)
+            .into_iter()
+            .chain(only_first.first().cloned())
+            .chain(only_second.first().cloned())
+            .min();
+
+        let mut summaries = BTreeMap::<(&'static str, Option<EntityId>), EntitySummary>::new();
+        for (tick, differences) in ticks.iter() {
+            for difference in differences.iter() {
+                let summary =
+                    summaries
+                        .entry((difference.kind, difference.id))
+                        .or_insert(EntitySummary {
+                            kind: difference.kind,
+                            id: difference.id,
+                            first_tick: *tick,
+                            last_tick: *tick,
+                            ticks: 0,
+                            max_offset: 0.,
+                        });
+                summary.last_tick = *tick;
+                summary.ticks += 1;
+                summary.max_offset = summary.max_offset.max(difference.offset);
+            }
+        }
+        let mut entities = summaries.into_values().collect::<Vec<_>>();
+        entities.sort_by_key(|summary| summary.first_tick);
+
+        let outcome = |recording: &Recording| recording.header.outcome.clone().unwrap_or_default();
+        let (first_outcome, second_outcome) = (outcome(first), outcome(second));
+        let teams = first_outcome.scores.len().max(second_outcome.scores.len());
+        let score = |scores: &[f32], team| scores.get(team).cloned().unwrap_or(0.);
+        RecordingDiff {
+            first_divergent_tick,
+            ticks,
+            only_first,
+            only_second,
+            entities,
+            winners: (first_outcome.winner, second_outcome.winner),
+            score_deltas: (0..teams)
+                .map(|team| {
+                    score(&second_outcome.scores, team) - score(&first_outcome.scores, team)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+    use crate::test_util::ant;
+    use crate::Outcome;
+
+    #[test]
+    fn matched_by_id() {
+        let before = Frame {
+            ants: vec![ant(1, 0, 0.), ant(2, 0, 0.), ant(3, 0, 0.)],
+            ..Frame::default()
+        };
+        let after = Frame {
+            tick: 1,
+            ants: vec![
+                ant(4, 0, 0.),
+                Ant {
+                    hp: 0.5,
+                    ..ant(3, 0, 0.)
+                },
+                ant(1, 0, 0.),
+            ],
+            ..Frame::default()
+        };
+        let differences = frame_differences(&before, &after);
+        let summary = differences
+            .iter()
+            .map(|difference| {
+                (
+                    difference.id,
+                    difference.before.is_some(),
+                    difference.after.is_some(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (Some(2), true, false),
+                (Some(3), true, true),
+                (Some(4), false, true)
+            ]
+        );
+        assert_eq!(
+            differences[1].after,
+            Some(EntityValue::Ant(after.ants[1].clone()))
+        );
+        assert!(frame_differences(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn recordings() {
+        let frame = |tick, x| Frame {
+            tick,
+            ants: vec![ant(1, 0, x)],
+            ..Frame::default()
+        };
+        let mut first = Recording::new();
+        first.frames = vec![frame(0, 0.), frame(1, 1.), frame(2, 2.)];
+        first.header.outcome = Some(Outcome {
+            winner: Some(0),
+            scores: vec![3.],
+        });
+        let mut second = Recording::new();
+        second.frames = vec![frame(0, 0.), frame(1, 1.5), frame(2, 4.), frame(3, 4.)];
+        second.header.outcome = Some(Outcome {
+            winner: Some(1),
+            scores: vec![1., 2.],
+        });
+
+        let diff = RecordingDiff::between(&first, &second);
+        assert_eq!(diff.first_divergent_tick, Some(1));
+        assert_eq!(diff.ticks.len(), 2);
+        assert_eq!(diff.only_second, vec![3]);
+        assert_eq!(diff.entities.len(), 1);
+        assert_eq!(diff.entities[0].ticks, 2);
+        assert_eq!(diff.entities[0].max_offset, 2.);
+        assert_eq!(diff.winners, (Some(0), Some(1)));
+        assert_eq!(diff.score_deltas, vec![-2., 2.]);
+
+        let same = RecordingDiff::between(&first, &first);
+        assert_eq!(same.first_divergent_tick, None);
+        assert!(same.entities.is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::objects::{EntityId, Team};
+use serde::{Deserialize, Serialize};
+
+/// Something that happened during a step of the simulation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    Spawn {
+        ant: EntityId,
+        team: Team,
+    },
+    Death {
+        ant: EntityId,
+        team: Team,
+    },
+    Pickup {
+        ant: EntityId,
+        team: Team,
+        sugar_hill: EntityId,
+    },
+    Delivery {
+        ant: EntityId,
+        team: Team,
+        anthill: EntityId,
+    },
+    FightHit {
+        attacker: EntityId,
+        team: Team,
+        target: EntityId,
+        damage: f32,
+    },
+    SmellEmitted {
+        smell_cloud: EntityId,
+        team: Team,
+        code: u32,
+    },
+    HillDepleted {
+        sugar_hill: EntityId,
+    },
+    MatchEnd {
+        winner: Option<Team>,
+    },
+}
+
+impl Event {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::Spawn { .. } => "spawn",
+            Event::Death { .. } => "death",
+            Event::Pickup { .. } => "pickup",
+            Event::Delivery { .. } => "delivery",
+            Event::FightHit { .. } => "fight_hit",
+            Event::SmellEmitted { .. } => "smell_emitted",
+            Event::HillDepleted { .. } => "hill_depleted",
+            Event::MatchEnd { .. } => "match_end",
+        }
+    }
+
+    /// Team causing the event
+    pub fn team(&self) -> Option<Team> {
+        match self {
+            Event::Spawn { team, .. }
+            | Event::Death { team, .. }
+            | Event::Pickup { team, .. }
+            | Event::Delivery { team, .. }
+            | Event::FightHit { team, .. }
+            | Event::SmellEmitted { team, .. } => Some(*team),
+            Event::HillDepleted { .. } => None,
+            Event::MatchEnd { winner } => *winner,
+        }
+    }
+
+    /// IDs of all involved entities, the causing one first
+    pub fn entities(&self) -> Vec<EntityId> {
+        match self {
+            Event::Spawn { ant, .. } | Event::Death { ant, .. } => vec![*ant],
+            Event::Pickup {
+                ant, sugar_hill, ..
+            } => vec![*ant, *sugar_hill],
+            Event::Delivery { ant, anthill, .. } => vec![*ant, *anthill],
+            Event::FightHit {
+                attacker, target, ..
+            } => vec![*attacker, *target],
+            Event::SmellEmitted { smell_cloud, .. } => vec![*smell_cloud],
+            Event::HillDepleted { sugar_hill } => vec![*sugar_hill],
+            Event::MatchEnd { .. } => vec![],
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! The only place where poses meet nalgebra. Rotations are in radians and
+//! counter-clockwise, poses coming out of an isometry have their rotation
+//! normalized to (-pi, pi].
+
+use crate::math::{normalize_angle, Pose, Vector2};
+use nalgebra::Isometry2;
+
+impl From<Pose> for Isometry2<f32> {
+    fn from(pose: Pose) -> Self {
+        Isometry2::new(nalgebra::Vector2::new(pose.x, pose.y), pose.rotation)
+    }
+}
+
+impl From<Isometry2<f32>> for Pose {
+    fn from(isometry: Isometry2<f32>) -> Self {
+        Pose {
+            x: isometry.translation.x,
+            y: isometry.translation.y,
+            rotation: normalize_angle(isometry.rotation.angle()),
+        }
+    }
+}
+
+impl From<Vector2> for Isometry2<f32> {
+    fn from(vector: Vector2) -> Self {
+        Isometry2::translation(vector.x, vector.y)
+    }
+}
+
+impl From<Isometry2<f32>> for Vector2 {
+    fn from(isometry: Isometry2<f32>) -> Self {
+        Vector2 {
+            x: isometry.translation.x,
+            y: isometry.translation.y,
+        }
+    }
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn round_trip() {
+        for &rotation in [0., 0.3, -2.9, PI, -PI, 4., 7.5].iter() {
+            let pose = Pose::new(3., -2., rotation);
+            let converted = Pose::from(Isometry2::from(pose));
+            assert!(converted.approx_eq(pose, 1e-5), "{:?}", converted);
+            assert!(converted.rotation > -PI && converted.rotation <= PI);
+        }
+        let isometry = Isometry2::new(nalgebra::Vector2::new(1., 2.), 0.5);
+        let converted = Isometry2::from(Pose::from(isometry));
+        assert!((converted.rotation.angle() - 0.5).abs() < 1e-6);
+        assert_eq!(converted.translation, isometry.translation);
+    }
+
+    #[test]
+    fn mirrored() {
+        let pose = Pose::new(3., -2., 0.5);
+        let mirrored = Isometry2::from(pose.mirror());
+        // Rotating by a half turn around the origin gives the same placement
+        let expected = Isometry2::rotation(PI) * Isometry2::from(pose);
+        assert!((mirrored.translation.vector - expected.translation.vector).norm() < 1e-5);
+        assert!(mirrored.rotation.angle_to(&expected.rotation).abs() < 1e-5);
+    }
+
+    #[test]
+    fn vectors() {
+        let vector = Vector2::new(3., 4.);
+        assert_eq!(Vector2::from(Isometry2::from(vector)), vector);
+    }
+}
@@ -1,51 +1,75 @@
 use serde::{Deserialize, Serialize};
 
+pub mod checksum;
+pub mod delta;
+pub mod diff;
+pub mod events;
+#[cfg(feature = "isometry")]
+pub mod isometry;
 pub mod math;
 pub mod objects;
+pub mod recording;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 
+pub use events::Event;
 pub use math::{Pose, Vector2};
-pub use objects::{Ant, AntHill, Map, SmellCloud, SugarHill, Team};
+pub use objects::{Ant, AntHill, EntityId, Map, SmellCloud, SugarHill, Team};
+pub use recording::{Header, Outcome, Recording};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Simulated seconds per tick of the engine
+pub const TIMESTEP: f32 = 0.1;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Frame {
+    pub tick: u64,
+    pub time: f32, // simulated seconds since the start
     pub ants: Vec<Ant>,
     pub anthills: Vec<AntHill>,
     pub raspberries: Vec<Pose>,
     pub sugar_hills: Vec<SugarHill>,
     pub smells_clouds: Vec<SmellCloud>,
+    pub events: Vec<Event>, // since the previous frame
+    pub checksum: u64,      // of the state when simulated, 0 if unknown
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Recording {
-    pub map: Map,
-    pub frames: Vec<Frame>,
-}
-
-impl Recording {
-    pub fn new() -> Self {
-        Recording {
-            map: Map {
-                width: 128.,
-                height: 128.,
-            },
-            frames: vec![],
-        }
+impl Frame {
+    /// Stable hash of the state, i.e. everything except events and the
+    /// stored checksum.
+    ///
+    /// Only covers what a frame holds. Simulation state outside of it, like
+    /// the map, the next entity ID or the exact rotations of which poses only
+    /// keep a rounded angle, is not hashed, so equal checksums do not prove
+    /// that two worlds continue the same way. Comparing saved worlds does.
+    pub fn state_checksum(&self) -> u64 {
+        checksum::checksum(&(
+            self.tick,
+            self.time,
+            &self.ants,
+            &self.anthills,
+            &self.raspberries,
+            &self.sugar_hills,
+            &self.smells_clouds,
+        ))
     }
+}
 
-    pub fn load<R>(reader: R) -> Result<Self, String>
-    where
-        R: std::io::Read,
-    {
-        bincode::deserialize_from::<R, Self>(reader).map_err(|msg| msg.to_string())
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn dump<W>(&self, writer: W) -> Result<(), String>
-    where
-        W: std::io::Write,
-    {
-        bincode::serialize_into(writer, self).map_err(|msg| msg.to_string())
+    #[test]
+    fn state_checksum() {
+        let mut frame = Frame {
+            tick: 3,
+            raspberries: vec![Pose::zero()],
+            ..Frame::default()
+        };
+        let checksum = frame.state_checksum();
+        frame.events.push(Event::HillDepleted { sugar_hill: 1 });
+        frame.checksum = checksum;
+        assert_eq!(frame.state_checksum(), checksum);
+        frame.raspberries[0].x = 1.;
+        assert_ne!(frame.state_checksum(), checksum);
     }
 }
-
-#[cfg(test)]
-mod tests {}
@@ -1,24 +1,267 @@
 use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Angles are in radians and counter-clockwise. Normalized angles lie in
+/// (-pi, pi].
+pub fn normalize_angle(angle: f32) -> f32 {
+    let angle = angle.rem_euclid(2. * PI);
+    if angle > PI {
+        angle - 2. * PI
+    } else {
+        angle
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl Vector2 {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn zero() -> Self {
+        Self::new(0., 0.)
+    }
+
+    pub fn dot(self, other: Vector2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    pub fn distance(self, other: Vector2) -> f32 {
+        (other - self).length()
+    }
+
+    /// Same direction with length 1, the zero vector stays zero
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        if length == 0. {
+            self
+        } else {
+            self * (1. / length)
+        }
+    }
+
+    /// Signed angle to rotate this direction onto the other one
+    pub fn angle_to(self, other: Vector2) -> f32 {
+        let cross = self.x * other.y - self.y * other.x;
+        normalize_angle(cross.atan2(self.dot(other)))
+    }
+
+    /// Rotated counter-clockwise around the origin
+    pub fn rotate(self, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos * self.x - sin * self.y, sin * self.x + cos * self.y)
+    }
+
+    pub fn approx_eq(self, other: Vector2, epsilon: f32) -> bool {
+        (self.x - other.x).abs() <= epsilon && (self.y - other.y).abs() <= epsilon
+    }
+}
+
+impl Add for Vector2 {
+    type Output = Vector2;
+
+    fn add(self, other: Vector2) -> Vector2 {
+        Vector2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Vector2 {
+    fn add_assign(&mut self, other: Vector2) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vector2 {
+    type Output = Vector2;
+
+    fn sub(self, other: Vector2) -> Vector2 {
+        Vector2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl SubAssign for Vector2 {
+    fn sub_assign(&mut self, other: Vector2) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f32> for Vector2 {
+    type Output = Vector2;
+
+    fn mul(self, factor: f32) -> Vector2 {
+        Vector2::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl Mul<Vector2> for f32 {
+    type Output = Vector2;
+
+    fn mul(self, vector: Vector2) -> Vector2 {
+        vector * self
+    }
+}
+
+impl Neg for Vector2 {
+    type Output = Vector2;
+
+    fn neg(self) -> Vector2 {
+        Vector2::new(-self.x, -self.y)
+    }
+}
+
+/// Position and heading of an object, or the transform from the object's
+/// local frame into the world.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Pose {
     pub x: f32,
     pub y: f32,
-    pub rotation: f32,
+    pub rotation: f32, // radians, counter-clockwise from the x axis
 }
 
 impl Pose {
+    pub fn new(x: f32, y: f32, rotation: f32) -> Self {
+        Self { x, y, rotation }
+    }
+
     pub fn zero() -> Self {
-        Self {
-            x: 0.,
-            y: 0.,
-            rotation: 0.,
-        }
+        Self::new(0., 0., 0.)
+    }
+
+    pub fn position(self) -> Vector2 {
+        Vector2::new(self.x, self.y)
+    }
+
+    /// Rotation normalized to (-pi, pi]
+    pub fn heading(self) -> f32 {
+        normalize_angle(self.rotation)
+    }
+
+    /// Point reflection through the origin, i.e. the center of the map. The
+    /// mirrored object faces the opposite direction.
+    pub fn mirror(self) -> Self {
+        Self::new(-self.x, -self.y, normalize_angle(self.rotation + PI))
+    }
+
+    /// Unit vector in the direction of the heading
+    pub fn direction(self) -> Vector2 {
+        Vector2::new(1., 0.).rotate(self.rotation)
+    }
+
+    /// `other` given relative to this pose, expressed in world coordinates.
+    /// Same as `self * other`.
+    pub fn compose(self, other: Pose) -> Self {
+        let position = self.to_world(other.position());
+        Self::new(
+            position.x,
+            position.y,
+            normalize_angle(self.rotation + other.rotation),
+        )
+    }
+
+    /// The pose which composed with this one gives the zero pose
+    pub fn inverse(self) -> Self {
+        let position = -self.position().rotate(-self.rotation);
+        Self::new(position.x, position.y, normalize_angle(-self.rotation))
+    }
+
+    /// Point in world coordinates seen from this pose, x pointing ahead
+    pub fn to_local(self, point: Vector2) -> Vector2 {
+        (point - self.position()).rotate(-self.rotation)
+    }
+
+    /// Point relative to this pose in world coordinates
+    pub fn to_world(self, point: Vector2) -> Vector2 {
+        self.position() + point.rotate(self.rotation)
+    }
+
+    /// Signed angle to turn from the heading towards the point
+    pub fn angle_to(self, point: Vector2) -> f32 {
+        self.direction().angle_to(point - self.position())
+    }
+
+    /// Rotations are compared modulo a full turn
+    pub fn approx_eq(self, other: Pose, epsilon: f32) -> bool {
+        self.position().approx_eq(other.position(), epsilon)
+            && normalize_angle(self.rotation - other.rotation).abs() <= epsilon
+    }
+}
+
+impl Mul for Pose {
+    type Output = Pose;
+
+    fn mul(self, other: Pose) -> Pose {
+        self.compose(other)
+    }
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    const EPSILON: f32 = 1e-5;
+
+    #[test]
+    fn angles() {
+        assert_eq!(normalize_angle(PI), PI);
+        assert_eq!(normalize_angle(-PI), PI);
+        assert!((normalize_angle(3. * PI / 2.) + FRAC_PI_2).abs() < EPSILON);
+        assert!((normalize_angle(-5. * PI / 2.) + FRAC_PI_2).abs() < EPSILON);
+    }
+
+    #[test]
+    fn vectors() {
+        let a = Vector2::new(3., 4.);
+        let b = Vector2::new(1., -1.);
+        assert_eq!(a + b, Vector2::new(4., 3.));
+        assert_eq!(a - b, Vector2::new(2., 5.));
+        assert_eq!(2. * a, a * 2.);
+        assert_eq!(-a, Vector2::new(-3., -4.));
+        assert_eq!(a.dot(b), -1.);
+        assert_eq!(a.length(), 5.);
+        assert_eq!(a.normalize(), Vector2::new(0.6, 0.8));
+        assert_eq!(Vector2::zero().normalize(), Vector2::zero());
+        assert!(Vector2::new(1., 0.)
+            .rotate(FRAC_PI_2)
+            .approx_eq(Vector2::new(0., 1.), EPSILON));
+        assert!((Vector2::new(1., 0.).angle_to(Vector2::new(0., -2.)) + FRAC_PI_2).abs() < EPSILON);
+        assert!(!a.approx_eq(b, EPSILON));
+    }
+
+    #[test]
+    fn poses() {
+        let pose = Pose::new(1., 2., FRAC_PI_2);
+        let point = Vector2::new(1., 3.);
+        let local = pose.to_local(point);
+        assert!(local.approx_eq(Vector2::new(1., 0.), EPSILON));
+        assert!(pose.to_world(local).approx_eq(point, EPSILON));
+        assert!(pose.angle_to(point).abs() < EPSILON);
+        assert!((pose.angle_to(Vector2::new(2., 2.)) + FRAC_PI_2).abs() < EPSILON);
+
+        let other = Pose::new(-2., 0.5, 2.);
+        assert!((pose * pose.inverse()).approx_eq(Pose::zero(), EPSILON));
+        assert!((pose.inverse() * pose).approx_eq(Pose::zero(), EPSILON));
+        let composed = pose * other;
+        assert!(composed
+            .to_world(point)
+            .approx_eq(pose.to_world(other.to_world(point)), EPSILON));
+        assert!(Pose::new(0., 0., PI).approx_eq(Pose::new(0., 0., -PI), EPSILON));
+
+        let mirrored = Pose::new(3., -2., 0.5).mirror();
+        assert!(mirrored.approx_eq(Pose::new(-3., 2., 0.5 - PI), EPSILON));
+        assert!(mirrored
+            .mirror()
+            .approx_eq(Pose::new(3., -2., 0.5), EPSILON));
+        assert!((Pose::new(0., 0., 7.).heading() - (7. - 2. * PI)).abs() < EPSILON);
     }
 }
@@ -2,36 +2,41 @@ use crate::math::{Pose, Vector2};
 use serde::{Deserialize, Serialize};
 
 pub type Team = u8;
+pub type EntityId = u32;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct SugarHill {
+    pub id: EntityId,
     pub pose: Pose,
     pub volume: f32,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AntHill {
+    pub id: EntityId,
     pub pose: Pose,
     pub team: Team,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Ant {
+    pub id: EntityId,
     pub pose: Pose,
     pub team: Team,
     pub hp: f32, // 0 - 1
     pub velocity: f32,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct SmellCloud {
+    pub id: EntityId,
     pub position: Vector2,
     pub code: u32,
     pub age: u32, // ins steps
     pub team: Team,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Map {
     pub width: f32,
     pub height: f32,
@@ -0,0 +1,1295 @@
+use crate::delta::FrameDelta;
+use crate::events::Event;
+use crate::objects::{Map, Team};
+use crate::Frame;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// First bytes of every recording file.
+pub const MAGIC: &[u8; 6] = b"ANTREC";
+
+/// Version of the on-disk layout. Bump it whenever `Header`, `Frame` or the
+/// layout after the magic bytes changes.
+pub const FORMAT_VERSION: u16 = 8;
+
+/// Default number of frames after which a full frame gets stored again.
+/// This is also the number of frames per block.
+pub const KEYFRAME_INTERVAL: usize = 64;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Outcome {
+    pub winner: Option<Team>,
+    pub scores: Vec<f32>, // indexed by team
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub engine_version: String,
+    pub seed: Option<u64>,
+    pub timestep: f32,      // simulated seconds per tick
+    pub teams: Vec<String>, // indexed by team
+    pub created: u64,       // seconds since unix epoch
+    /// Only known once the match ended, so files store it in the `End` entry
+    pub outcome: Option<Outcome>,
+}
+
+impl Header {
+    pub fn new() -> Self {
+        Header {
+            engine_version: String::new(),
+            seed: None,
+            timestep: crate::TIMESTEP,
+            teams: vec![],
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            outcome: None,
+        }
+    }
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Recording {
+    pub header: Header,
+    pub map: Map,
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Recording {
+            header: Header::new(),
+            map: Map {
+                width: 128.,
+                height: 128.,
+            },
+            frames: vec![],
+        }
+    }
+
+    /// The frame shown at the given simulated time, i.e. the last frame not
+    /// later than `time`.
+    pub fn frame_at(&self, time: f32) -> Option<&Frame> {
+        match self.frames.partition_point(|frame| frame.time <= time) {
+            0 => None,
+            index => self.frames.get(index - 1),
+        }
+    }
+
+    pub fn frame_by_tick(&self, tick: u64) -> Option<&Frame> {
+        self.frames
+            .binary_search_by_key(&tick, |frame| frame.tick)
+            .ok()
+            .map(|index| &self.frames[index])
+    }
+
+    /// All events together with the tick of the frame they were stored in.
+    pub fn events(&self) -> impl Iterator<Item = (u64, &Event)> {
+        self.frames
+            .iter()
+            .flat_map(|frame| frame.events.iter().map(move |event| (frame.tick, event)))
+    }
+
+    /// Keeps the frames within the tick range and renumbers them, so that
+    /// the earliest one is at tick 0 and time 0. Shifted frames with a
+    /// checksum get the one of their new state. Frames are sorted by tick,
+    /// as lookups like `frame_by_tick` expect.
+    pub fn trim(mut self, ticks: Range<u64>) -> Self {
+        self.frames.retain(|frame| ticks.contains(&frame.tick));
+        self.frames.sort_by_key(|frame| frame.tick);
+        if let Some(first) = self.frames.first() {
+            let (tick, time) = (first.tick, first.time);
+            for frame in self.frames.iter_mut() {
+                rebase(frame, frame.tick - tick, frame.time - time);
+            }
+        }
+        self
+    }
+
+    /// Keeps every nth frame and the last one, together with their ticks.
+    /// Events of dropped frames are moved to the next kept frame.
+    pub fn downsample(mut self, n: usize) -> Self {
+        if n <= 1 {
+            return self;
+        }
+        let last = self.frames.len().saturating_sub(1);
+        let mut events = Vec::new();
+        let mut index = 0;
+        self.frames.retain_mut(|frame| {
+            let keep = index % n == 0 || index == last;
+            index += 1;
+            if keep {
+                events.append(&mut frame.events);
+                std::mem::swap(&mut frame.events, &mut events);
+            } else {
+                events.append(&mut frame.events);
+            }
+            keep
+        });
+        self
+    }
+
+    /// Appends the frames of another recording of the same map and teams,
+    /// continuing the tick numbering after the latest frame. The outcome is
+    /// taken from the other recording. Frames of both are sorted by tick.
+    pub fn append(&mut self, mut other: Recording) -> Result<(), String> {
+        if self.map != other.map {
+            return Err("Recordings have different maps".to_owned());
+        }
+        if self.header.teams != other.header.teams {
+            return Err("Recordings have different teams".to_owned());
+        }
+        if self.header.timestep != other.header.timestep {
+            return Err("Recordings have different timesteps".to_owned());
+        }
+        self.frames.sort_by_key(|frame| frame.tick);
+        other.frames.sort_by_key(|frame| frame.tick);
+        let (next_tick, next_time) = match self.frames.last() {
+            Some(last) => (last.tick + 1, last.time + self.header.timestep),
+            None => (0, 0.),
+        };
+        let (first_tick, first_time) = other
+            .frames
+            .first()
+            .map_or((0, 0.), |first| (first.tick, first.time));
+        self.frames
+            .extend(other.frames.into_iter().map(|mut frame| {
+                let (tick, time) = (
+                    frame.tick - first_tick + next_tick,
+                    frame.time - first_time + next_time,
+                );
+                rebase(&mut frame, tick, time);
+                frame
+            }));
+        self.header.outcome = other.header.outcome;
+        Ok(())
+    }
+
+    pub fn load<R>(reader: R) -> Result<Self, String>
+    where
+        R: std::io::Read,
+    {
+        let mut reader = RecordingReader::new(reader)?;
+        let frames = reader.by_ref().collect::<Result<Vec<_>, _>>()?;
+        let RecordingReader { header, map, .. } = reader;
+        Ok(Recording {
+            header,
+            map,
+            frames,
+        })
+    }
+
+    pub fn dump<W>(&self, writer: W) -> Result<(), String>
+    where
+        W: std::io::Write,
+    {
+        let mut writer = RecordingWriter::new(writer, &self.header, &self.map)?;
+        for frame in self.frames.iter() {
+            writer.write_frame(frame)?;
+        }
+        writer.finish(self.header.outcome.clone())?;
+        Ok(())
+    }
+}
+
+/// Moves the frame to another tick and time. Both are part of the state,
+/// so a known checksum is recomputed.
+fn rebase(frame: &mut Frame, tick: u64, time: f32) {
+    if frame.tick != tick || frame.time != time {
+        frame.tick = tick;
+        frame.time = time;
+        if frame.checksum != 0 {
+            frame.checksum = frame.state_checksum();
+        }
+    }
+}
+
+/// JSON and MessagePack share the schema given by the serde derives, with
+/// field names in both, so that tools outside of Rust can read recordings.
+#[cfg(feature = "export")]
+impl Recording {
+    pub fn to_json<W>(&self, writer: W) -> Result<(), String>
+    where
+        W: Write,
+    {
+        serde_json::to_writer(writer, self).map_err(|msg| msg.to_string())
+    }
+
+    pub fn from_json<R>(reader: R) -> Result<Self, String>
+    where
+        R: Read,
+    {
+        serde_json::from_reader(reader).map_err(|msg| msg.to_string())
+    }
+
+    pub fn to_msgpack<W>(&self, mut writer: W) -> Result<(), String>
+    where
+        W: Write,
+    {
+        rmp_serde::encode::write_named(&mut writer, self).map_err(|msg| msg.to_string())
+    }
+
+    pub fn from_msgpack<R>(reader: R) -> Result<Self, String>
+    where
+        R: Read,
+    {
+        rmp_serde::from_read(reader).map_err(|msg| msg.to_string())
+    }
+}
+
+impl Default for Recording {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `Header` as stored at the start of recording files. The outcome is only
+/// known once the writer finishes, so it is stored in the `End` entry.
+#[derive(Serialize, Deserialize)]
+struct FileHeader {
+    engine_version: String,
+    seed: Option<u64>,
+    timestep: f32,
+    teams: Vec<String>,
+    created: u64,
+}
+
+impl From<&Header> for FileHeader {
+    fn from(header: &Header) -> Self {
+        FileHeader {
+            engine_version: header.engine_version.clone(),
+            seed: header.seed,
+            timestep: header.timestep,
+            teams: header.teams.clone(),
+            created: header.created,
+        }
+    }
+}
+
+impl From<FileHeader> for Header {
+    fn from(header: FileHeader) -> Self {
+        Header {
+            engine_version: header.engine_version,
+            seed: header.seed,
+            timestep: header.timestep,
+            teams: header.teams,
+            created: header.created,
+            outcome: None,
+        }
+    }
+}
+
+/// Everything after the header and the map is a sequence of entries.
+/// Frames are grouped into blocks which start with a keyframe, so that each
+/// block can be decoded on its own. A missing `End` entry means the writer
+/// did not finish, e.g. because the match crashed, but all blocks written
+/// until then can still be read.
+#[derive(Serialize, Deserialize)]
+enum Entry {
+    /// Followed by `length` bytes of frame entries
+    Block(BlockInfo),
+    End(Option<Outcome>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct BlockInfo {
+    first_tick: u64,
+    frames: u32,
+    compressed: bool,
+    length: u64,
+}
+
+/// Each frame is either stored completely or as delta to its predecessor.
+#[derive(Serialize, Deserialize)]
+enum FrameEntry<F, D> {
+    Key(F),
+    Delta(D),
+}
+
+/// Position of a block in the recording file.
+/// The list of all blocks is stored after the `End` entry, followed by its
+/// offset and `INDEX_MAGIC`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub first_frame: u64,
+    pub first_tick: u64,
+    pub frames: u32,
+    pub offset: u64,
+}
+
+const INDEX_MAGIC: &[u8; 6] = b"ANTIDX";
+const FOOTER_SIZE: u64 = 8 + INDEX_MAGIC.len() as u64;
+
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W> Write for CountingWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "compression")]
+fn compress(data: &[u8], level: i32) -> Result<Vec<u8>, String> {
+    zstd::encode_all(data, level).map_err(|msg| msg.to_string())
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress(_data: &[u8], _level: i32) -> Result<Vec<u8>, String> {
+    Err(NO_COMPRESSION.to_owned())
+}
+
+#[cfg(feature = "compression")]
+fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    zstd::decode_all(data).map_err(|msg| msg.to_string())
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(_data: &[u8]) -> Result<Vec<u8>, String> {
+    Err(NO_COMPRESSION.to_owned())
+}
+
+#[cfg(not(feature = "compression"))]
+const NO_COMPRESSION: &str =
+    "Recording is compressed but common was built without the compression feature";
+
+fn is_eof(error: &bincode::ErrorKind) -> bool {
+    match error {
+        bincode::ErrorKind::Io(io) => io.kind() == std::io::ErrorKind::UnexpectedEof,
+        _ => false,
+    }
+}
+
+/// Appends frames to a recording file as they are produced.
+/// Frames are buffered until their block is complete or `flush` is called.
+/// A writer dropped without `finish`, e.g. while a crashing match unwinds,
+/// still writes its buffered frames but leaves the recording unfinished.
+pub struct RecordingWriter<W>
+where
+    W: Write,
+{
+    writer: Option<CountingWriter<W>>, // None once finished
+    previous: Option<Frame>,
+    keyframe_interval: usize,
+    compression: Option<i32>,
+    block: Vec<u8>,
+    block_frames: u32,
+    block_tick: u64,
+    frames: u64,
+    index: Vec<IndexEntry>,
+}
+
+impl<W> RecordingWriter<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W, header: &Header, map: &Map) -> Result<Self, String> {
+        let mut writer = CountingWriter {
+            inner: writer,
+            written: 0,
+        };
+        write_preamble(&mut writer)?;
+        bincode::serialize_into(&mut writer, &FileHeader::from(header))
+            .map_err(|msg| msg.to_string())?;
+        bincode::serialize_into(&mut writer, map).map_err(|msg| msg.to_string())?;
+        Ok(RecordingWriter {
+            writer: Some(writer),
+            previous: None,
+            keyframe_interval: KEYFRAME_INTERVAL,
+            compression: None,
+            block: Vec::new(),
+            block_frames: 0,
+            block_tick: 0,
+            frames: 0,
+            index: Vec::new(),
+        })
+    }
+
+    /// A full frame gets stored at least every `interval` frames.
+    /// Smaller intervals mean bigger files but less work when seeking.
+    pub fn set_keyframe_interval(&mut self, interval: usize) {
+        self.keyframe_interval = interval.max(1);
+    }
+
+    /// Compresses each block with zstd at the given level.
+    /// Needs the `compression` feature.
+    pub fn set_compression(&mut self, level: Option<i32>) -> Result<(), String> {
+        if level.is_some() && cfg!(not(feature = "compression")) {
+            compress(&[], 0)?;
+        }
+        self.compression = level;
+        Ok(())
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), String> {
+        if self.block_frames as usize >= self.keyframe_interval {
+            self.write_block()?;
+        }
+        let delta = match &self.previous {
+            Some(previous) if self.block_frames > 0 => FrameDelta::try_between(previous, frame),
+            _ => None,
+        };
+        let entry = match &delta {
+            Some(delta) => FrameEntry::Delta(delta),
+            None => FrameEntry::Key(frame),
+        };
+        bincode::serialize_into(&mut self.block, &entry).map_err(|msg| msg.to_string())?;
+        if self.block_frames == 0 {
+            self.block_tick = frame.tick;
+        }
+        self.block_frames += 1;
+        self.previous = Some(frame.clone());
+        Ok(())
+    }
+
+    /// Writes the buffered frames, so that they survive even if the process
+    /// gets killed. The next frame starts a new block with a keyframe.
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.write_block()
+    }
+
+    fn write_block(&mut self) -> Result<(), String> {
+        let writer = match &mut self.writer {
+            Some(writer) if self.block_frames > 0 => writer,
+            _ => return Ok(()),
+        };
+        let compressed = match self.compression {
+            Some(level) => Some(compress(&self.block, level)?),
+            None => None,
+        };
+        let data = compressed.as_ref().unwrap_or(&self.block);
+        self.index.push(IndexEntry {
+            first_frame: self.frames,
+            first_tick: self.block_tick,
+            frames: self.block_frames,
+            offset: writer.written,
+        });
+        let info = BlockInfo {
+            first_tick: self.block_tick,
+            frames: self.block_frames,
+            compressed: compressed.is_some(),
+            length: data.len() as u64,
+        };
+        bincode::serialize_into(&mut *writer, &Entry::Block(info))
+            .map_err(|msg| msg.to_string())?;
+        writer
+            .write_all(data)
+            .and_then(|_| writer.flush())
+            .map_err(|msg| msg.to_string())?;
+        self.frames += self.block_frames as u64;
+        self.block.clear();
+        self.block_frames = 0;
+        Ok(())
+    }
+
+    /// Marks the recording as complete and returns the underlying writer.
+    pub fn finish(mut self, outcome: Option<Outcome>) -> Result<W, String> {
+        self.write_block()?;
+        let mut writer = self.writer.take().ok_or("Recording was already finished")?;
+        bincode::serialize_into(&mut writer, &Entry::End(outcome))
+            .map_err(|msg| msg.to_string())?;
+        let index_offset = writer.written;
+        bincode::serialize_into(&mut writer, &self.index).map_err(|msg| msg.to_string())?;
+        writer
+            .write_all(&index_offset.to_le_bytes())
+            .and_then(|_| writer.write_all(INDEX_MAGIC))
+            .and_then(|_| writer.flush())
+            .map_err(|msg| msg.to_string())?;
+        Ok(writer.inner)
+    }
+}
+
+impl<W> Drop for RecordingWriter<W>
+where
+    W: Write,
+{
+    fn drop(&mut self) {
+        // Errors can not be reported here, the frames are lost either way
+        let _ = self.write_block();
+    }
+}
+
+/// Reads the frames of a recording file one after another.
+/// Once the last frame was read, `header.outcome` holds the outcome the
+/// writer was finished with.
+/// If the underlying reader is seekable, single frames or ranges can be
+/// loaded without decoding the blocks before them.
+pub struct RecordingReader<R>
+where
+    R: Read,
+{
+    pub header: Header,
+    pub map: Map,
+    reader: R,
+    previous: Option<Frame>,
+    pending: VecDeque<Frame>,
+    index: Option<Vec<IndexEntry>>,
+    data_start: u64,
+    done: bool,
+}
+
+impl<R> RecordingReader<R>
+where
+    R: Read,
+{
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        read_preamble(&mut reader)?;
+        let header = bincode::deserialize_from::<_, FileHeader>(&mut reader)
+            .map_err(|msg| format!("Could not read recording header: {}", msg))?;
+        let map =
+            bincode::deserialize_from::<_, Map>(&mut reader).map_err(|msg| msg.to_string())?;
+        let data_start = (MAGIC.len() + 2) as u64
+            + bincode::serialized_size(&header).map_err(|msg| msg.to_string())?
+            + bincode::serialized_size(&map).map_err(|msg| msg.to_string())?;
+        Ok(RecordingReader {
+            header: header.into(),
+            map,
+            reader,
+            previous: None,
+            pending: VecDeque::new(),
+            index: None,
+            data_start,
+            done: false,
+        })
+    }
+
+    /// Reads the next entry and decodes all frames of it.
+    fn read_entry(&mut self) -> Result<(), String> {
+        let entry = match bincode::deserialize_from::<_, Entry>(&mut self.reader) {
+            Ok(entry) => entry,
+            // Unfinished recording
+            Err(error) if is_eof(&error) => {
+                self.done = true;
+                return Ok(());
+            }
+            Err(error) => return Err(error.to_string()),
+        };
+        match entry {
+            Entry::Block(info) => {
+                // The length is not trusted for allocating, as the block
+                // may be cut off or the length be corrupt
+                let mut data = Vec::new();
+                (&mut self.reader)
+                    .take(info.length)
+                    .read_to_end(&mut data)
+                    .map_err(|msg| msg.to_string())?;
+                if (data.len() as u64) < info.length {
+                    return Err(format!(
+                        "Block of {} bytes ends after {} bytes",
+                        info.length,
+                        data.len()
+                    ));
+                }
+                if info.compressed {
+                    data = decompress(&data)?;
+                }
+                let mut data = &data[..];
+                for _ in 0..info.frames {
+                    let frame = match bincode::deserialize_from(&mut data)
+                        .map_err(|msg| msg.to_string())?
+                    {
+                        FrameEntry::<Frame, FrameDelta>::Key(frame) => frame,
+                        FrameEntry::Delta(delta) => delta.apply(
+                            self.previous
+                                .as_ref()
+                                .ok_or("Block starts with a delta frame")?,
+                        ),
+                    };
+                    self.previous = Some(frame.clone());
+                    self.pending.push_back(frame);
+                }
+            }
+            Entry::End(outcome) => {
+                self.done = true;
+                self.header.outcome = outcome;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R> RecordingReader<R>
+where
+    R: Read + Seek,
+{
+    /// Location of all blocks. Read from the end of the file or, for
+    /// unfinished recordings, by skipping through all blocks.
+    /// Iterating continues where it was.
+    pub fn index(&mut self) -> Result<&[IndexEntry], String> {
+        if self.index.is_none() {
+            let position = self
+                .reader
+                .stream_position()
+                .map_err(|msg| msg.to_string())?;
+            let index = self.read_index().map_err(|msg| msg.to_string());
+            self.reader
+                .seek(SeekFrom::Start(position))
+                .map_err(|msg| msg.to_string())?;
+            self.index = Some(index?);
+        }
+        Ok(self.index.as_deref().unwrap_or_default())
+    }
+
+    fn read_index(&mut self) -> Result<Vec<IndexEntry>, Box<dyn std::error::Error>> {
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        if end >= self.data_start + FOOTER_SIZE {
+            self.reader.seek(SeekFrom::End(-(FOOTER_SIZE as i64)))?;
+            let mut footer = [0u8; FOOTER_SIZE as usize];
+            self.reader.read_exact(&mut footer)?;
+            if &footer[8..] == INDEX_MAGIC {
+                let mut offset = [0u8; 8];
+                offset.copy_from_slice(&footer[..8]);
+                self.reader
+                    .seek(SeekFrom::Start(u64::from_le_bytes(offset)))?;
+                return Ok(bincode::deserialize_from(&mut self.reader)?);
+            }
+        }
+        let mut index = Vec::new();
+        let mut offset = self.reader.seek(SeekFrom::Start(self.data_start))?;
+        let mut first_frame = 0;
+        while let Ok(Entry::Block(info)) = bincode::deserialize_from(&mut self.reader) {
+            let next = self.reader.seek(SeekFrom::Current(info.length as i64))?;
+            if next > end {
+                break;
+            }
+            index.push(IndexEntry {
+                first_frame,
+                first_tick: info.first_tick,
+                frames: info.frames,
+                offset,
+            });
+            first_frame += info.frames as u64;
+            offset = next;
+        }
+        Ok(index)
+    }
+
+    /// Outcome of a finished recording without decoding the frames.
+    /// Iterating continues where it was.
+    pub fn outcome(&mut self) -> Result<Option<Outcome>, String> {
+        let position = self
+            .reader
+            .stream_position()
+            .map_err(|msg| msg.to_string())?;
+        let end = match self.index()?.last().cloned() {
+            Some(block) => {
+                self.reader
+                    .seek(SeekFrom::Start(block.offset))
+                    .map_err(|msg| msg.to_string())?;
+                match bincode::deserialize_from(&mut self.reader) {
+                    Ok(Entry::Block(info)) => self
+                        .reader
+                        .seek(SeekFrom::Current(info.length as i64))
+                        .map_err(|msg| msg.to_string())?,
+                    _ => return Err("Index points to an invalid block".to_owned()),
+                }
+            }
+            None => self.data_start,
+        };
+        self.reader
+            .seek(SeekFrom::Start(end))
+            .map_err(|msg| msg.to_string())?;
+        let outcome = match bincode::deserialize_from(&mut self.reader) {
+            Ok(Entry::End(outcome)) => outcome,
+            _ => None, // unfinished
+        };
+        self.reader
+            .seek(SeekFrom::Start(position))
+            .map_err(|msg| msg.to_string())?;
+        self.header.outcome = outcome.clone();
+        Ok(outcome)
+    }
+
+    pub fn frame_count(&mut self) -> Result<usize, String> {
+        Ok(self
+            .index()?
+            .last()
+            .map(|block| block.first_frame as usize + block.frames as usize)
+            .unwrap_or(0))
+    }
+
+    /// Continues reading at the given frame.
+    pub fn seek_frame(&mut self, frame: usize) -> Result<(), String> {
+        let frame = frame as u64;
+        let block = self
+            .index()?
+            .iter()
+            .find(|block| {
+                block.first_frame <= frame && frame < block.first_frame + block.frames as u64
+            })
+            .cloned()
+            .ok_or_else(|| format!("Frame {} is out of range", frame))?;
+        self.reader
+            .seek(SeekFrom::Start(block.offset))
+            .map_err(|msg| msg.to_string())?;
+        self.pending.clear();
+        self.previous = None;
+        self.done = false;
+        self.read_entry()?;
+        let skip = ((frame - block.first_frame) as usize).min(self.pending.len());
+        self.pending.drain(..skip);
+        Ok(())
+    }
+
+    /// Continues reading at the first frame with at least the given tick.
+    pub fn seek_tick(&mut self, tick: u64) -> Result<(), String> {
+        let index = self.index()?;
+        let block = match index
+            .iter()
+            .take_while(|block| block.first_tick <= tick)
+            .last()
+            .or_else(|| index.first())
+        {
+            Some(block) => block.clone(),
+            // No frames at all
+            None => {
+                self.pending.clear();
+                self.done = true;
+                return Ok(());
+            }
+        };
+        self.seek_frame(block.first_frame as usize)?;
+        while let Some(frame) = self.pending.front() {
+            if frame.tick >= tick {
+                return Ok(());
+            }
+            self.pending.pop_front();
+        }
+        // All frames of this block are before the tick, so continue with the next block
+        Ok(())
+    }
+
+    pub fn frame(&mut self, frame: usize) -> Result<Frame, String> {
+        self.seek_frame(frame)?;
+        self.next()
+            .unwrap_or_else(|| Err(format!("Frame {} is out of range", frame)))
+    }
+
+    pub fn frames(&mut self, range: Range<usize>) -> Result<Vec<Frame>, String> {
+        if range.start >= range.end {
+            return Ok(vec![]);
+        }
+        self.seek_frame(range.start)?;
+        self.by_ref().take(range.end - range.start).collect()
+    }
+}
+
+impl<R> Iterator for RecordingReader<R>
+where
+    R: Read,
+{
+    type Item = Result<Frame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(frame) = self.pending.pop_front() {
+                return Some(Ok(frame));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(msg) = self.read_entry() {
+                self.done = true;
+                return Some(Err(msg));
+            }
+        }
+    }
+}
+
+fn write_preamble<W>(writer: &mut W) -> Result<(), String>
+where
+    W: std::io::Write,
+{
+    writer
+        .write_all(MAGIC)
+        .and_then(|_| writer.write_all(&FORMAT_VERSION.to_le_bytes()))
+        .map_err(|msg| msg.to_string())
+}
+
+fn read_preamble<R>(reader: &mut R) -> Result<(), String>
+where
+    R: std::io::Read,
+{
+    let mut magic = [0u8; 6];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|msg| format!("Could not read recording header: {}", msg))?;
+    if &magic != MAGIC {
+        return Err(
+            "Not a recording file or written before recordings had a header (magic bytes missing)"
+                .to_owned(),
+        );
+    }
+    let mut version = [0u8; 2];
+    reader
+        .read_exact(&mut version)
+        .map_err(|msg| format!("Could not read recording format version: {}", msg))?;
+    let version = u16::from_le_bytes(version);
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported recording format version {} (this build reads version {})",
+            version, FORMAT_VERSION
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod specs {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut recording = Recording::new();
+        recording.header.seed = Some(42);
+        recording.header.teams = vec!["red".to_owned(), "blue".to_owned()];
+        recording.frames.push(Frame::default());
+        let mut buffer = Vec::new();
+        recording.dump(&mut buffer).unwrap();
+
+        let loaded = Recording::load(&buffer[..]).unwrap();
+        assert_eq!(loaded.header.seed, Some(42));
+        assert_eq!(loaded.header.teams, recording.header.teams);
+        assert_eq!(loaded.header.timestep, crate::TIMESTEP);
+        assert_eq!(loaded.frames.len(), 1);
+    }
+
+    #[test]
+    fn streaming() {
+        let mut header = Header::new();
+        header.outcome = Some(Outcome {
+            winner: None,
+            scores: vec![],
+        });
+        let map = Map {
+            width: 12.,
+            height: 8.,
+        };
+        let mut writer = RecordingWriter::new(Vec::new(), &header, &map).unwrap();
+        for _ in 0..3 {
+            writer.write_frame(&Frame::default()).unwrap();
+        }
+        let buffer = writer
+            .finish(Some(Outcome {
+                winner: Some(1),
+                scores: vec![2., 5.],
+            }))
+            .unwrap();
+
+        let mut reader = RecordingReader::new(&buffer[..]).unwrap();
+        assert_eq!(reader.map.width, 12.);
+        assert!(reader.header.outcome.is_none());
+        assert_eq!(reader.by_ref().filter(Result::is_ok).count(), 3);
+        let outcome = reader.header.outcome.unwrap();
+        assert_eq!(outcome.winner, Some(1));
+        assert_eq!(outcome.scores, vec![2., 5.]);
+
+        let mut reader = RecordingReader::new(std::io::Cursor::new(buffer)).unwrap();
+        assert_eq!(reader.outcome().unwrap().unwrap().winner, Some(1));
+        assert_eq!(reader.filter(Result::is_ok).count(), 3);
+    }
+
+    #[test]
+    fn unfinished() {
+        let header = Header::new();
+        let map = Map {
+            width: 12.,
+            height: 8.,
+        };
+        let mut buffer = Vec::new();
+        let mut writer = RecordingWriter::new(&mut buffer, &header, &map).unwrap();
+        for tick in 0..3 {
+            writer
+                .write_frame(&Frame {
+                    tick,
+                    ..Frame::default()
+                })
+                .unwrap();
+        }
+        // Crashed in the middle of the block
+        drop(writer);
+
+        let recording = Recording::load(&buffer[..]).unwrap();
+        assert_eq!(recording.frames.len(), 3);
+        assert!(recording.header.outcome.is_none());
+
+        let mut reader = RecordingReader::new(std::io::Cursor::new(buffer)).unwrap();
+        assert_eq!(reader.frame_count().unwrap(), 3);
+        assert_eq!(reader.frame(2).unwrap().tick, 2);
+        assert!(reader.outcome().unwrap().is_none());
+    }
+
+    /// Writes the frames and kills the writer without giving it a chance
+    /// to write its buffered frames
+    fn killed(frames: &[Frame], flush_after: usize) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let mut writer =
+            RecordingWriter::new(&mut buffer, &Header::new(), &Recording::new().map).unwrap();
+        writer.set_keyframe_interval(16);
+        for (i, frame) in frames.iter().enumerate() {
+            writer.write_frame(frame).unwrap();
+            if i + 1 == flush_after {
+                writer.flush().unwrap();
+            }
+        }
+        std::mem::forget(writer);
+        buffer
+    }
+
+    #[test]
+    fn killed_after_flush() {
+        let frames = moving_ants(40);
+        let buffer = killed(&frames, 37);
+        let recording = Recording::load(&buffer[..]).unwrap();
+        assert_eq!(recording.frames, frames[..37].to_vec());
+        assert!(recording.header.outcome.is_none());
+
+        // Continues with a new block after flushing
+        let buffer = killed(&frames, 20);
+        let mut reader = RecordingReader::new(std::io::Cursor::new(buffer)).unwrap();
+        assert_eq!(reader.frame_count().unwrap(), 36);
+        assert_eq!(reader.index().unwrap()[2].first_frame, 20);
+    }
+
+    #[test]
+    fn index_keeps_position() {
+        let frames = moving_ants(40);
+        let finished = dump(&frames, None);
+        // Only the first two blocks, without index
+        let unfinished = killed(&frames, 0);
+
+        for (buffer, count) in [(finished, 40), (unfinished, 32)] {
+            let mut reader = RecordingReader::new(std::io::Cursor::new(buffer)).unwrap();
+            assert_eq!(reader.next().unwrap().unwrap(), frames[0]);
+            assert_eq!(reader.next().unwrap().unwrap(), frames[1]);
+            assert_eq!(reader.frame_count().unwrap(), count);
+            let rest = reader.map(|frame| frame.unwrap()).collect::<Vec<_>>();
+            assert_eq!(rest, frames[2..count].to_vec());
+        }
+    }
+
+    fn moving_ants(ticks: usize) -> Vec<Frame> {
+        let mut frame = Frame::default();
+        for id in 0..32 {
+            frame.anthills.push(crate::AntHill {
+                id: 1000 + id,
+                pose: crate::Pose::zero(),
+                team: 0,
+            });
+            frame.ants.push(crate::Ant {
+                velocity: 1.,
+                ..crate::test_util::ant(id, 0, 0.)
+            });
+        }
+        (0..ticks)
+            .map(|tick| {
+                frame.tick = tick as u64;
+                frame.time = tick as f32 * 0.5;
+                frame.ants[tick % 32].pose.x += 1.;
+                frame.checksum = frame.state_checksum();
+                frame.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deltas() {
+        let frames = moving_ants(200);
+        let mut full = Vec::new();
+        let mut writer =
+            RecordingWriter::new(&mut full, &Header::new(), &Recording::new().map).unwrap();
+        writer.set_keyframe_interval(1);
+        frames
+            .iter()
+            .for_each(|frame| writer.write_frame(frame).unwrap());
+        writer.finish(None).unwrap();
+
+        let mut compact = Vec::new();
+        let mut recording = Recording::new();
+        recording.frames = frames.clone();
+        recording.dump(&mut compact).unwrap();
+
+        assert!(compact.len() * 5 < full.len());
+        assert_eq!(Recording::load(&full[..]).unwrap().frames, frames);
+        assert_eq!(Recording::load(&compact[..]).unwrap().frames, frames);
+    }
+
+    fn dump(frames: &[Frame], compression: Option<i32>) -> Vec<u8> {
+        let mut writer =
+            RecordingWriter::new(Vec::new(), &Header::new(), &Recording::new().map).unwrap();
+        writer.set_keyframe_interval(16);
+        writer.set_compression(compression).unwrap();
+        for frame in frames.iter() {
+            writer.write_frame(frame).unwrap();
+        }
+        writer.finish(None).unwrap()
+    }
+
+    #[test]
+    fn seek() {
+        let frames = moving_ants(100);
+        let mut reader = RecordingReader::new(std::io::Cursor::new(dump(&frames, None))).unwrap();
+        assert_eq!(reader.frame_count().unwrap(), 100);
+        assert_eq!(reader.index().unwrap().len(), 7);
+        assert_eq!(reader.frame(37).unwrap(), frames[37]);
+        assert_eq!(reader.frame(3).unwrap(), frames[3]);
+        assert_eq!(reader.frames(90..95).unwrap(), &frames[90..95]);
+        assert_eq!(reader.frames(95..120).unwrap(), &frames[95..]);
+        assert!(reader.frame(100).is_err());
+        // Continues after the sought frame
+        reader.seek_frame(98).unwrap();
+        assert_eq!(reader.count(), 2);
+    }
+
+    #[test]
+    fn seek_tick() {
+        // Every third tick
+        let frames = moving_ants(300).into_iter().step_by(3).collect::<Vec<_>>();
+        let mut reader = RecordingReader::new(std::io::Cursor::new(dump(&frames, None))).unwrap();
+        reader.seek_tick(151).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap().tick, 153);
+        reader.seek_tick(45).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), frames[15]);
+        // Right behind the last frame of a block
+        reader.seek_tick(frames[15].tick + 1).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), frames[16]);
+        reader.seek_tick(1000).unwrap();
+        assert!(reader.next().is_none());
+
+        // Before the first frame
+        let mut reader =
+            RecordingReader::new(std::io::Cursor::new(dump(&frames[5..], None))).unwrap();
+        reader.seek_tick(0).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), frames[5]);
+
+        let mut reader = RecordingReader::new(std::io::Cursor::new(dump(&[], None))).unwrap();
+        reader.seek_tick(0).unwrap();
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn trim() {
+        let mut recording = Recording::new();
+        recording.frames = moving_ants(100);
+        let trimmed = recording.trim(10..20);
+        assert_eq!(trimmed.frames.len(), 10);
+        assert_eq!(trimmed.frames[0].tick, 0);
+        assert_eq!(trimmed.frames[0].time, 0.);
+        assert_eq!(trimmed.frames[9].tick, 9);
+        assert_eq!(trimmed.frames[9].time, 4.5);
+        assert_eq!(trimmed.frames[0].ants, moving_ants(11)[10].ants);
+        assert_ne!(trimmed.frames[0].checksum, moving_ants(11)[10].checksum);
+        assert!(trimmed
+            .frames
+            .iter()
+            .all(|frame| frame.checksum == frame.state_checksum()));
+
+        // Not sorted by tick
+        let mut recording = Recording::new();
+        recording.frames = moving_ants(20);
+        recording.frames.swap(5, 12);
+        let trimmed = recording.trim(5..15);
+        let ticks = trimmed
+            .frames
+            .iter()
+            .map(|frame| frame.tick)
+            .collect::<Vec<_>>();
+        assert_eq!(ticks, (0..10).collect::<Vec<_>>());
+        assert_eq!(trimmed.frames[0].ants, moving_ants(6)[5].ants);
+    }
+
+    #[test]
+    fn downsample() {
+        let mut recording = Recording::new();
+        recording.frames = moving_ants(10);
+        for frame in recording.frames.iter_mut() {
+            frame.events.push(Event::HillDepleted {
+                sugar_hill: frame.tick as u32,
+            });
+        }
+        let downsampled = recording.downsample(4);
+        let ticks = downsampled
+            .frames
+            .iter()
+            .map(|frame| frame.tick)
+            .collect::<Vec<_>>();
+        assert_eq!(ticks, vec![0, 4, 8, 9]);
+        assert_eq!(downsampled.frames[1].events.len(), 4);
+        assert_eq!(downsampled.events().count(), 10);
+        assert_ne!(downsampled.frames[1].checksum, 0);
+    }
+
+    #[test]
+    fn append() {
+        let mut first = Recording::new();
+        first.header.timestep = 0.5;
+        first.frames = moving_ants(10);
+        let mut second = Recording::new();
+        second.header.timestep = 0.5;
+        second.header.outcome = Some(Outcome {
+            winner: Some(1),
+            scores: vec![],
+        });
+        second.frames = moving_ants(20).split_off(5);
+        first.append(second).unwrap();
+        assert_eq!(first.frames.len(), 25);
+        assert_eq!(first.frames[10].tick, 10);
+        assert_eq!(first.frames[24].tick, 24);
+        assert_eq!(first.frames[24].time, 12.);
+        assert_eq!(first.header.outcome.unwrap().winner, Some(1));
+        assert_eq!(first.frames[24].checksum, first.frames[24].state_checksum());
+
+        // Not sorted by tick
+        let mut first = Recording::new();
+        first.header.timestep = 0.5;
+        first.frames = moving_ants(4);
+        first.frames.swap(1, 3);
+        let mut second = Recording::new();
+        second.header.timestep = 0.5;
+        second.frames = moving_ants(8).split_off(5);
+        second.frames.swap(0, 2);
+        first.append(second).unwrap();
+        let ticks = first
+            .frames
+            .iter()
+            .map(|frame| frame.tick)
+            .collect::<Vec<_>>();
+        assert_eq!(ticks, (0..7).collect::<Vec<_>>());
+        assert_eq!(first.frames[6].time, 3.);
+        assert_eq!(first.frames[6].ants, moving_ants(8)[7].ants);
+
+        let mut other = Recording::new();
+        other.map.width = 1.;
+        assert!(Recording::new().append(other).is_err());
+    }
+
+    #[test]
+    fn events() {
+        let mut recording = Recording::new();
+        recording.frames = moving_ants(4);
+        recording.frames[0].events = vec![
+            Event::Spawn { ant: 0, team: 0 },
+            Event::Spawn { ant: 1, team: 0 },
+        ];
+        recording.frames[3].events = vec![Event::Death { ant: 1, team: 0 }];
+        let mut buffer = Vec::new();
+        recording.dump(&mut buffer).unwrap();
+        let loaded = Recording::load(&buffer[..]).unwrap();
+        assert_eq!(loaded.frames, recording.frames);
+        let deaths = loaded
+            .events()
+            .filter(|(_, event)| event.kind() == "death")
+            .map(|(tick, event)| (tick, event.entities()))
+            .collect::<Vec<_>>();
+        assert_eq!(deaths, vec![(3, vec![1])]);
+    }
+
+    #[test]
+    fn frame_at() {
+        let mut recording = Recording::new();
+        recording.frames = moving_ants(10);
+        assert_eq!(recording.frame_at(-1.), None);
+        assert_eq!(recording.frame_at(0.).unwrap().tick, 0);
+        assert_eq!(recording.frame_at(1.2).unwrap().tick, 2);
+        assert_eq!(recording.frame_at(100.).unwrap().tick, 9);
+        assert_eq!(recording.frame_by_tick(4).unwrap().time, 2.);
+        assert!(recording.frame_by_tick(10).is_none());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed() {
+        let frames = moving_ants(100);
+        let plain = dump(&frames, None);
+        let compressed = dump(&frames, Some(3));
+        assert!(compressed.len() < plain.len());
+        assert_eq!(Recording::load(&compressed[..]).unwrap().frames, frames);
+        let mut reader = RecordingReader::new(std::io::Cursor::new(compressed)).unwrap();
+        assert_eq!(reader.frame(50).unwrap(), frames[50]);
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn compression_unavailable() {
+        let mut writer =
+            RecordingWriter::new(Vec::new(), &Header::new(), &Recording::new().map).unwrap();
+        assert!(writer.set_compression(Some(3)).is_err());
+    }
+
+    #[cfg(feature = "export")]
+    #[test]
+    fn export() {
+        let mut recording = Recording::new();
+        recording.header.teams = vec!["red".to_owned()];
+        recording.header.timestep = 0.5; // as in moving_ants
+        recording.frames = moving_ants(3);
+
+        let mut json = Vec::new();
+        recording.to_json(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["header"]["teams"][0], "red");
+        assert_eq!(value["frames"][2]["ants"][2]["pose"]["x"], 1.);
+        assert_eq!(
+            Recording::from_json(&json[..]).unwrap().frames,
+            recording.frames
+        );
+
+        let mut msgpack = Vec::new();
+        recording.to_msgpack(&mut msgpack).unwrap();
+        let from_msgpack = Recording::from_msgpack(&msgpack[..]).unwrap();
+        assert_eq!(from_msgpack.frames, recording.frames);
+        // Same schema in both formats
+        let value_from_msgpack: serde_json::Value = rmp_serde::from_slice(&msgpack).unwrap();
+        assert_eq!(value_from_msgpack, value);
+    }
+
+    #[test]
+    fn block_cut_off() {
+        let mut buffer = dump(&moving_ants(20), None);
+        let reader = RecordingReader::new(&buffer[..100]).unwrap();
+        assert!(reader.last().unwrap().is_err());
+
+        // Length field of the first block, behind the variant tag, first tick,
+        // frame count and compression flag
+        let reader = RecordingReader::new(&buffer[..]).unwrap();
+        let position = reader.data_start as usize + 4 + 8 + 4 + 1;
+        buffer[position..position + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let error = Recording::load(&buffer[..]).unwrap_err();
+        assert!(error.contains("ends after"));
+    }
+
+    #[test]
+    fn missing_magic() {
+        let error = Recording::load(&b"not a recording"[..]).unwrap_err();
+        assert!(error.contains("magic"));
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut buffer = MAGIC.to_vec();
+        buffer.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let error = Recording::load(&buffer[..]).unwrap_err();
+        assert!(error.contains("Unsupported recording format version"));
+    }
+}
@@ -0,0 +1,14 @@
+//! Fixtures for tests, also of other crates via the `test-util` feature.
+
+use crate::{Ant, EntityId, Pose, Team};
+
+/// Healthy ant at rest at (x, 0), facing along the x axis
+pub fn ant(id: EntityId, team: Team, x: f32) -> Ant {
+    Ant {
+        id,
+        pose: Pose::new(x, 0., 0.),
+        team,
+        hp: 1.,
+        velocity: 0.,
+    }
+}
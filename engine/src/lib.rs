@@ -1,3 +1,6 @@
 pub mod world;
 
-pub use world::World;
+pub use world::{World, TIMESTEP};
+
+/// Version of the engine, stored in the header of every recording.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
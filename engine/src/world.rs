@@ -1,10 +1,13 @@
-use common::{Ant, AntHill, Frame, Map, Pose, SmellCloud, SugarHill, Team, Vector2};
-use nalgebra::Isometry2;
+use common::{
+    Ant, AntHill, EntityId, Event, Frame, Map, Pose, SmellCloud, SugarHill, Team, Vector2,
+};
+use nalgebra::{Complex, Isometry2, Translation2, UnitComplex};
 use ncollide2d::bounding_volume;
 use ncollide2d::bounding_volume::BoundingVolume;
 use ncollide2d::pipeline::{CollisionGroups, CollisionObjectSlabHandle, GeometricQueryType};
 use ncollide2d::shape::{Ball, ShapeHandle};
 use ncollide2d::world::CollisionWorld;
+use serde::{Deserialize, Serialize};
 
 trait Mirror {
     fn mirror(&self) -> Self;
@@ -12,10 +15,11 @@ trait Mirror {
 
 impl Mirror for Isometry2<f32> {
     fn mirror(&self) -> Self {
-        Isometry2::from_parts(self.translation.inverse(), -self.rotation)
+        Isometry2::from(Pose::from(*self).mirror())
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 enum MapItem {
     Anthill(AntHill),
     Ant(Ant),
@@ -24,37 +28,43 @@ enum MapItem {
     SmellCloud(SmellCloud),
 }
 
-trait Convert<Target> {
-    fn convert(&self) -> Target;
-}
-
 const ANT_HILL_RADIUS: f32 = 3.5;
 const ANT_RADIUS: f32 = 0.5;
 const SUGAR_HILL_RADIUS: f32 = 2.;
 const RASPBERRY_RADIUS: f32 = 0.5;
 const SMELL_CLOUD_RADIUS: f32 = 3.;
+pub use common::TIMESTEP;
 
-impl Convert<Pose> for nalgebra::Isometry2<f32> {
-    fn convert(&self) -> Pose {
-        Pose {
-            x: self.translation.x,
-            y: self.translation.y,
-            rotation: self.rotation.into_inner().re,
-        }
-    }
+/// Version of the save file layout
+const SAVE_VERSION: u16 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SavedObject {
+    x: f32,
+    y: f32,
+    // Rotation as unit complex number, so that it gets restored bit by bit
+    re: f32,
+    im: f32,
+    item: MapItem,
 }
 
-impl Convert<Vector2> for nalgebra::Isometry2<f32> {
-    fn convert(&self) -> Vector2 {
-        Vector2 {
-            x: self.translation.x,
-            y: self.translation.y,
-        }
-    }
+#[derive(Serialize, Deserialize)]
+struct SavedWorld {
+    version: u16,
+    map: Map,
+    tick: u64,
+    next_id: EntityId,
+    events: Vec<Event>,
+    // In the iteration order of the collision world, which is the slot order
+    // of its slab and not necessarily the insertion order. Restoring inserts
+    // them into an empty world in this order, so snapshots list items in the
+    // same order as before saving.
+    objects: Vec<SavedObject>,
 }
 
 pub struct World {
     pub map: Map,
+    pub tick: u64,
     world: CollisionWorld<f32, MapItem>,
     ant_hill_shape: ShapeHandle<f32>,
     sugar_hill_shape: ShapeHandle<f32>,
@@ -68,12 +78,15 @@ pub struct World {
     ants: Vec<CollisionObjectSlabHandle>,      // really needed?
     raspberries: Vec<CollisionObjectSlabHandle>, // really needed?
     smell_clouds: Vec<CollisionObjectSlabHandle>, // really needed?
+    next_id: EntityId,
+    events: Vec<Event>, // of the current step
 }
 
 impl World {
     pub fn new(width: f32, height: f32) -> Self {
         Self {
             map: Map { width, height },
+            tick: 0,
             world: CollisionWorld::new(0.02f32),
             ant_hill_shape: ShapeHandle::new(Ball::new(ANT_HILL_RADIUS)),
             sugar_hill_shape: ShapeHandle::new(Ball::new(SUGAR_HILL_RADIUS)),
@@ -89,9 +102,17 @@ impl World {
             sugar_hills: Vec::new(),
             raspberries: Vec::new(),
             smell_clouds: Vec::new(),
+            next_id: 0,
+            events: Vec::new(),
         }
     }
 
+    fn create_id(&mut self) -> EntityId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
     /// Call this after adding items to the world
     pub fn update(&mut self) {
         self.world.update()
@@ -99,10 +120,17 @@ impl World {
 
     pub fn step(&mut self) {
         // TODO: move, act, remove, add new items
+        self.events.clear();
         self.world.update();
+        self.tick += 1;
         unimplemented!();
     }
 
+    /// Events since the last step
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
     pub fn sense(&self) {
         unimplemented!();
         // Find collisions
@@ -111,12 +139,14 @@ impl World {
     }
 
     pub fn add_ant_hill(&mut self, position: Isometry2<f32>, team: Team) {
+        let id = self.create_id();
         let (handle, _) = self.world.add(
             position,
             self.ant_hill_shape.clone(),
             self.collision_groups,
             GeometricQueryType::Contacts(0.0, 0.0),
             MapItem::Anthill(AntHill {
+                id,
                 pose: Pose::zero(),
                 team,
             }),
@@ -164,12 +194,14 @@ impl World {
     }
 
     pub fn add_sugar_hill(&mut self, position: Isometry2<f32>) {
+        let id = self.create_id();
         let (handle, _) = self.world.add(
             position,
             self.sugar_hill_shape.clone(),
             self.collision_groups,
             GeometricQueryType::Contacts(0.0, 0.0),
             MapItem::SugarHill(SugarHill {
+                id,
                 pose: Pose::zero(),
                 volume: 1.,
             }),
@@ -218,12 +250,14 @@ impl World {
     }
 
     pub fn add_ant(&mut self, position: Isometry2<f32>, team: Team) {
+        let id = self.create_id();
         let (handle, _) = self.world.add(
             position,
             self.ant_shape.clone(),
             self.collision_groups,
             GeometricQueryType::Contacts(0.0, 0.0),
             MapItem::Ant(Ant {
+                id,
                 pose: Pose::zero(),
                 team,
                 hp: 1.,
@@ -231,6 +265,7 @@ impl World {
             }),
         );
         self.ants.push(handle);
+        self.events.push(Event::Spawn { ant: id, team });
     }
 
     pub fn try_add_ant(&mut self, position: Isometry2<f32>, team: Team) -> Option<()> {
@@ -243,7 +278,7 @@ impl World {
         {
             None
         } else {
-            self.add_ant_hill(position, team);
+            self.add_ant(position, team);
             Some(())
         }
     }
@@ -300,19 +335,155 @@ impl World {
     }
 
     pub fn add_smell(&mut self, position: Isometry2<f32>, code: u32, team: Team) {
+        let id = self.create_id();
         let (handle, _) = self.world.add(
             position,
             self.smell_cloud_shape.clone(),
             self.smell_collision_groups,
             GeometricQueryType::Contacts(0.0, 0.0),
             MapItem::SmellCloud(SmellCloud {
-                position: position.convert(),
+                id,
+                position: Vector2::from(position),
                 code,
                 age: 0,
                 team,
             }),
         );
         self.smell_clouds.push(handle);
+        self.events.push(Event::SmellEmitted {
+            smell_cloud: id,
+            team,
+            code,
+        });
+    }
+
+    /// Writes the complete state, which `restore` turns into an identical world.
+    pub fn save<W>(&self, writer: W) -> Result<(), String>
+    where
+        W: std::io::Write,
+    {
+        let objects = self
+            .world
+            .collision_objects()
+            .map(|(_, object)| {
+                let position = object.position();
+                let rotation = position.rotation.into_inner();
+                SavedObject {
+                    x: position.translation.x,
+                    y: position.translation.y,
+                    re: rotation.re,
+                    im: rotation.im,
+                    item: object.data().clone(),
+                }
+            })
+            .collect();
+        let saved = SavedWorld {
+            version: SAVE_VERSION,
+            map: self.map.clone(),
+            tick: self.tick,
+            next_id: self.next_id,
+            events: self.events.clone(),
+            objects,
+        };
+        bincode::serialize_into(writer, &saved).map_err(|msg| msg.to_string())
+    }
+
+    pub fn restore<R>(reader: R) -> Result<Self, String>
+    where
+        R: std::io::Read,
+    {
+        let saved = bincode::deserialize_from::<R, SavedWorld>(reader)
+            .map_err(|msg| format!("Could not read save file: {}", msg))?;
+        if saved.version != SAVE_VERSION {
+            return Err(format!(
+                "Unsupported save file version {} (this build reads version {})",
+                saved.version, SAVE_VERSION
+            ));
+        }
+        let mut world = World::new(saved.map.width, saved.map.height);
+        for object in saved.objects.into_iter() {
+            let position = Isometry2::from_parts(
+                Translation2::new(object.x, object.y),
+                UnitComplex::new_unchecked(Complex::new(object.re, object.im)),
+            );
+            world.insert(position, object.item);
+        }
+        world.tick = saved.tick;
+        world.next_id = saved.next_id;
+        world.events = saved.events;
+        world.update();
+        Ok(world)
+    }
+
+    /// Builds a world containing exactly the items of the frame, e.g. to set
+    /// up hand-authored scenarios. Items keep their IDs and state.
+    pub fn from_frame(frame: &Frame, map: &Map) -> Self {
+        let mut world = World::new(map.width, map.height);
+        for anthill in frame.anthills.iter() {
+            world.insert(
+                Isometry2::from(anthill.pose),
+                MapItem::Anthill(anthill.clone()),
+            );
+        }
+        for sugar_hill in frame.sugar_hills.iter() {
+            world.insert(
+                Isometry2::from(sugar_hill.pose),
+                MapItem::SugarHill(sugar_hill.clone()),
+            );
+        }
+        for raspberry in frame.raspberries.iter() {
+            world.insert(
+                Isometry2::from(*raspberry),
+                MapItem::Raspberry(Pose::zero()),
+            );
+        }
+        for ant in frame.ants.iter() {
+            world.insert(Isometry2::from(ant.pose), MapItem::Ant(ant.clone()));
+        }
+        for smell_cloud in frame.smells_clouds.iter() {
+            world.insert(
+                Isometry2::from(smell_cloud.position),
+                MapItem::SmellCloud(smell_cloud.clone()),
+            );
+        }
+        world.tick = frame.tick;
+        world.next_id = frame
+            .ants
+            .iter()
+            .map(|ant| ant.id)
+            .chain(frame.anthills.iter().map(|anthill| anthill.id))
+            .chain(frame.sugar_hills.iter().map(|sugar_hill| sugar_hill.id))
+            .chain(frame.smells_clouds.iter().map(|smell_cloud| smell_cloud.id))
+            .max()
+            .map_or(0, |id| id + 1);
+        world.update();
+        world
+    }
+
+    /// Adds an item without placement checks or creating a new ID
+    fn insert(&mut self, position: Isometry2<f32>, item: MapItem) {
+        let (shape, groups) = match &item {
+            MapItem::Anthill(_) => (&self.ant_hill_shape, self.collision_groups),
+            MapItem::Ant(_) => (&self.ant_shape, self.collision_groups),
+            MapItem::SugarHill(_) => (&self.sugar_hill_shape, self.collision_groups),
+            MapItem::Raspberry(_) => (&self.raspberry_shape, self.collision_groups),
+            MapItem::SmellCloud(_) => (&self.smell_cloud_shape, self.smell_collision_groups),
+        };
+        let handles = match &item {
+            MapItem::Anthill(_) => &mut self.ant_hills,
+            MapItem::Ant(_) => &mut self.ants,
+            MapItem::SugarHill(_) => &mut self.sugar_hills,
+            MapItem::Raspberry(_) => &mut self.raspberries,
+            MapItem::SmellCloud(_) => &mut self.smell_clouds,
+        };
+        let (handle, _) = self.world.add(
+            position,
+            shape.clone(),
+            groups,
+            GeometricQueryType::Contacts(0.0, 0.0),
+            item,
+        );
+        handles.push(handle);
     }
 
     pub fn snapshot(&self) -> Frame {
@@ -325,34 +496,40 @@ impl World {
             match object.data() {
                 MapItem::Anthill(anthill) => {
                     let mut candidate = anthill.clone();
-                    candidate.pose = object.position().convert();
+                    candidate.pose = Pose::from(*object.position());
                     anthills.push(candidate)
                 }
                 MapItem::Ant(ant) => {
                     let mut candidate = ant.clone();
-                    candidate.pose = object.position().convert();
+                    candidate.pose = Pose::from(*object.position());
                     ants.push(candidate)
                 }
                 MapItem::SugarHill(sugar_hill) => {
                     let mut candidate = sugar_hill.clone();
-                    candidate.pose = object.position().convert();
+                    candidate.pose = Pose::from(*object.position());
                     sugar_hills.push(candidate)
                 }
                 MapItem::SmellCloud(smells_cloud) => {
                     let mut candidate = smells_cloud.clone();
-                    candidate.position = object.position().convert();
+                    candidate.position = Vector2::from(*object.position());
                     smells_clouds.push(candidate)
                 }
-                MapItem::Raspberry(_) => raspberries.push(object.position().convert()),
+                MapItem::Raspberry(_) => raspberries.push(Pose::from(*object.position())),
             }
         }
-        Frame {
+        let mut frame = Frame {
+            tick: self.tick,
+            time: self.tick as f32 * TIMESTEP,
             ants,
             anthills,
             raspberries,
             sugar_hills,
             smells_clouds,
-        }
+            events: self.events.clone(),
+            checksum: 0,
+        };
+        frame.checksum = frame.state_checksum();
+        frame
     }
 }
 
@@ -362,7 +539,7 @@ mod specs {
     #[test]
     fn mirrored_self_collision() {
         let mut world = World::new(32., 32.);
-        let result = world.try_add_ant_hill_mirrored(
+        let result = world.add_ant_hill_mirrored(
             Isometry2::new(nalgebra::Vector2::new(0., 0.), nalgebra::zero()),
             0,
         );
@@ -370,10 +547,169 @@ mod specs {
     }
 
     #[test]
-    fn mirrored_fine() {
+    fn spawn_events() {
         let mut world = World::new(32., 32.);
-        let result = world.try_add_ant_hill_mirrored(
+        world.add_ant(
             Isometry2::new(nalgebra::Vector2::new(3., 2.), nalgebra::zero()),
+            1,
+        );
+        let frame = world.snapshot();
+        assert_eq!(
+            frame.events,
+            vec![Event::Spawn {
+                ant: frame.ants[0].id,
+                team: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn try_add_ant() {
+        let mut world = World::new(32., 32.);
+        let position = Isometry2::new(nalgebra::Vector2::new(3., 2.), nalgebra::zero());
+        assert!(world.try_add_ant(position, 1).is_some());
+        world.update();
+        let frame = world.snapshot();
+        assert_eq!(frame.ants.len(), 1);
+        assert_eq!(frame.ants[0].team, 1);
+        assert!(frame.anthills.is_empty());
+        assert!(world.try_add_ant(position, 1).is_none());
+    }
+
+    #[test]
+    fn save_and_restore() {
+        let mut world = World::new(32., 48.);
+        world.add_ant_hill(Isometry2::new(nalgebra::Vector2::new(3., 2.), 0.3), 1);
+        world.add_ant(Isometry2::new(nalgebra::Vector2::new(-3., 2.), 1.7), 1);
+        world.add_raspberry(Isometry2::new(nalgebra::Vector2::new(8., -2.), -2.9));
+        world.add_smell(Isometry2::new(nalgebra::Vector2::new(1., 1.), 0.), 4, 1);
+        world.update();
+
+        let mut buffer = Vec::new();
+        world.save(&mut buffer).unwrap();
+        let mut restored = World::restore(&buffer[..]).unwrap();
+
+        assert_eq!(restored.snapshot(), world.snapshot());
+        let mut buffer_again = Vec::new();
+        restored.save(&mut buffer_again).unwrap();
+        assert_eq!(buffer_again, buffer);
+
+        // Continues with the same IDs
+        world.add_sugar_hill(Isometry2::new(nalgebra::Vector2::new(9., 9.), 0.));
+        restored.add_sugar_hill(Isometry2::new(nalgebra::Vector2::new(9., 9.), 0.));
+        assert_eq!(restored.snapshot(), world.snapshot());
+    }
+
+    #[test]
+    fn from_frame() {
+        let frame = Frame {
+            tick: 12,
+            ants: vec![Ant {
+                id: 7,
+                pose: Pose {
+                    x: 1.,
+                    y: 2.,
+                    rotation: std::f32::consts::FRAC_PI_2,
+                },
+                team: 1,
+                hp: 0.5,
+                velocity: 0.3,
+            }],
+            sugar_hills: vec![SugarHill {
+                id: 3,
+                pose: Pose {
+                    x: 3.,
+                    y: 2.,
+                    rotation: 0.,
+                },
+                volume: 4.,
+            }],
+            ..Frame::default()
+        };
+        let mut world = World::from_frame(
+            &frame,
+            &Map {
+                width: 16.,
+                height: 16.,
+            },
+        );
+        let snapshot = world.snapshot();
+        assert_eq!(snapshot.tick, 12);
+        assert_eq!(snapshot.checksum, snapshot.state_checksum());
+        assert_eq!(snapshot.ants.len(), 1);
+        assert_eq!(snapshot.ants[0].id, 7);
+        assert_eq!(snapshot.ants[0].hp, 0.5);
+        assert_eq!(snapshot.ants[0].velocity, 0.3);
+        assert!((snapshot.ants[0].pose.x - 1.).abs() < 1e-6);
+        assert!((snapshot.ants[0].pose.y - 2.).abs() < 1e-6);
+        assert_eq!(snapshot.sugar_hills[0].volume, 4.);
+
+        // New items get fresh IDs
+        world.add_ant(Isometry2::new(nalgebra::Vector2::new(8., 8.), 0.), 0);
+        let ids = world
+            .snapshot()
+            .ants
+            .iter()
+            .map(|ant| ant.id)
+            .collect::<Vec<_>>();
+        assert!(ids.contains(&8));
+    }
+
+    #[test]
+    fn snapshot_rotations() {
+        let mut world = World::new(32., 32.);
+        let rotations = [0., 0.3, 2.1, -2.9, std::f32::consts::PI, 4.];
+        for (i, &rotation) in rotations.iter().enumerate() {
+            world.add_ant(Isometry2::from(Pose::new(i as f32, 1., rotation)), 0);
+        }
+        world.update();
+        let snapshot = world.snapshot();
+        assert_eq!(snapshot.ants.len(), rotations.len());
+        for ant in snapshot.ants.iter() {
+            let expected = Pose::new(ant.pose.x, 1., rotations[ant.pose.x.round() as usize]);
+            assert!(ant.pose.approx_eq(expected, 1e-5), "{:?}", ant.pose);
+            assert_eq!(ant.pose.rotation, ant.pose.heading());
+        }
+
+        // Snapshots survive being turned into a world again
+        let restored = World::from_frame(&snapshot, &world.map);
+        for (ant, expected) in restored.snapshot().ants.iter().zip(snapshot.ants.iter()) {
+            assert!(ant.pose.approx_eq(expected.pose, 1e-5));
+        }
+    }
+
+    #[test]
+    fn mirrored_placements() {
+        let mut world = World::new(32., 32.);
+        let pose = Pose::new(8., 5., 0.5);
+        assert!(world
+            .add_ant_hill_mirrored(Isometry2::from(pose), 0)
+            .is_some());
+        assert!(world
+            .add_raspberry_mirrored(Isometry2::from(Pose::new(-4., 9., -2.)))
+            .is_some());
+        world.update();
+        let snapshot = world.snapshot();
+        let poses = snapshot
+            .anthills
+            .iter()
+            .map(|anthill| anthill.pose)
+            .collect::<Vec<_>>();
+        assert_eq!(poses.len(), 2);
+        assert!(poses.iter().any(|other| other.approx_eq(pose, 1e-5)));
+        let mirrored = Pose::new(-8., -5., 0.5 - std::f32::consts::PI);
+        assert!(poses.iter().any(|other| other.approx_eq(mirrored, 1e-5)));
+        assert!(snapshot
+            .raspberries
+            .iter()
+            .any(|other| other.approx_eq(Pose::new(4., -9., std::f32::consts::PI - 2.), 1e-5)));
+    }
+
+    #[test]
+    fn mirrored_fine() {
+        let mut world = World::new(32., 32.);
+        let result = world.add_ant_hill_mirrored(
+            Isometry2::new(nalgebra::Vector2::new(8., 5.), nalgebra::zero()),
             0,
         );
         assert!(result.is_some());
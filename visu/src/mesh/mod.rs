@@ -15,6 +15,18 @@ pub struct Transformation {
     pub scale: f32,
 }
 
+impl Transformation {
+    /// Places a model at the pose, lifted by `z` and turned about the z axis
+    /// by `Pose::heading`
+    pub fn at(pose: common::Pose, z: f32, scale: f32) -> Self {
+        Transformation {
+            translation: Vector3::new(pose.x, pose.y, z),
+            rotation: Vector3::new(0., 0., pose.heading()),
+            scale,
+        }
+    }
+}
+
 pub struct SimpleMesh {
     shader: WebGlProgram,
     vertex_buffer: WebGlBuffer,
@@ -11,8 +11,6 @@ use yew::{html, Component, ComponentLink, Html, NodeRef, ShouldRender};
 
 use common;
 
-use nalgebra::Vector3;
-
 use crate::background::Background;
 use crate::camera::Camera;
 use crate::ground::Ground;
@@ -59,7 +57,7 @@ impl From<i16> for MouseButton {
 
 #[derive(Debug)]
 pub enum FetchErrorReason {
-    Deserialize,
+    Deserialize(String),
     NotOk,
     NotSuccess,
 }
@@ -129,6 +127,7 @@ pub struct Scene {
     resize_service: Option<Box<ResizeTask>>,
     models: Models,
     recording: Option<common::Recording>,
+    playback_start: Option<f64>,
     ground: Option<Ground>,
     camera: Camera,
     background: Option<Background>,
@@ -153,8 +152,8 @@ impl Scene {
                     if let Ok(data) = data {
                         match common::Recording::load(&data[..]) {
                             Ok(data) => Msg::FetchResponse(data),
-                            _ => Msg::FailedFetch(FetchError {
-                                reason: FetchErrorReason::Deserialize,
+                            Err(msg) => Msg::FailedFetch(FetchError {
+                                reason: FetchErrorReason::Deserialize(msg),
                                 uri,
                             }),
                         }
@@ -202,6 +201,7 @@ impl Component for Scene {
                 button: MouseButton::Left,
             },
             recording: None,
+            playback_start: None,
             // fetch_service: FetchService::new(),
             fetch_task: None,
         }
@@ -330,7 +330,8 @@ impl Component for Scene {
             Msg::FetchResponse(recording) => {
                 let gl = self.gl.as_ref().expect("GL Context not initialized!");
                 self.ground = Some(Ground::new(&gl, recording.map.width, recording.map.height));
-                self.recording = Some(recording)
+                self.recording = Some(recording);
+                self.playback_start = None;
             }
         }
         false
@@ -390,67 +391,39 @@ impl Scene {
         }
         gl.enable(GL::DEPTH_TEST);
 
-        if let Some(recording) = &self.recording {
+        // Play back in simulated time
+        let playback_start = *self.playback_start.get_or_insert(timestamp);
+        let time = ((timestamp - playback_start) / 1000.) as f32;
+        let frame = self.recording.as_ref().and_then(|recording| {
+            let start = recording.frames.first().map(|frame| frame.time)?;
+            recording.frame_at(start + time)
+        });
+
+        if let Some(frame) = frame {
             if let Some(ant) = &self.models.ant {
-                for inst in recording.frames[0].ants.iter() {
-                    let rotation = Vector3::new(0.0f32, 0.0f32, inst.pose.rotation);
-                    let translation = Vector3::new(inst.pose.x, inst.pose.y, 0.8f32);
-                    ant.render(
-                        &gl,
-                        &self.camera,
-                        &Transformation {
-                            rotation,
-                            translation,
-                            scale: 0.5,
-                        },
-                    );
+                for inst in frame.ants.iter() {
+                    let transformation = Transformation::at(inst.pose, 0.8, 0.5);
+                    ant.render(&gl, &self.camera, &transformation);
                 }
             }
             if let Some(raspberry) = &self.models.raspberry {
-                for inst in recording.frames[0].raspberries.iter() {
-                    let rotation = Vector3::new(0.0f32, 0.0f32, inst.rotation);
-                    let translation = Vector3::new(inst.x, inst.y, 0.8f32);
-                    raspberry.render(
-                        &gl,
-                        &self.camera,
-                        &Transformation {
-                            rotation,
-                            translation,
-                            scale: 10.0,
-                        },
-                    );
+                for inst in frame.raspberries.iter() {
+                    let transformation = Transformation::at(*inst, 0.8, 10.0);
+                    raspberry.render(&gl, &self.camera, &transformation);
                 }
             }
 
             if let Some(anthill) = &self.models.anthill {
-                for inst in recording.frames[0].anthills.iter() {
-                    let rotation = Vector3::new(0.0f32, 0.0f32, inst.pose.rotation);
-                    let translation = Vector3::new(inst.pose.x, inst.pose.y, 0.);
-                    anthill.render(
-                        &gl,
-                        &self.camera,
-                        &Transformation {
-                            rotation,
-                            translation,
-                            scale: 5.0,
-                        },
-                    );
+                for inst in frame.anthills.iter() {
+                    let transformation = Transformation::at(inst.pose, 0., 5.0);
+                    anthill.render(&gl, &self.camera, &transformation);
                 }
             }
 
             if let Some(sugar_hill) = &self.models.sugar_hill {
-                for inst in recording.frames[0].sugar_hills.iter() {
-                    let rotation = Vector3::new(0.0f32, 0.0f32, inst.pose.rotation);
-                    let translation = Vector3::new(inst.pose.x, inst.pose.y, 0.);
-                    sugar_hill.render(
-                        &gl,
-                        &self.camera,
-                        &Transformation {
-                            rotation,
-                            translation,
-                            scale: 10.0,
-                        },
-                    );
+                for inst in frame.sugar_hills.iter() {
+                    let transformation = Transformation::at(inst.pose, 0., 10.0);
+                    sugar_hill.render(&gl, &self.camera, &transformation);
                 }
             }
         }